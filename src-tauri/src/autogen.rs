@@ -0,0 +1,162 @@
+// Writes to ~/.config/hypr/configs/autogen. Every write goes to a temporary
+// file that is renamed into place, and the version it replaces is kept as a
+// timestamped backup that can be restored from the app.
+use crate::home_dir;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Backups kept per file; older ones are deleted on write
+const MAX_BACKUPS: usize = 20;
+
+// Written at the top of every generated file
+pub const AUTOGEN_HEADER: &str = "\
+###############################################################
+## DO NOT EDIT THIS FILE!                                    ##
+## This file is automatically generated by Archion Settings. ##
+###############################################################
+";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigBackup {
+    // Autogen file the backup belongs to, e.g. "monitors.conf"
+    pub file: String,
+    // Backup file name, passed back to restore_config_backup
+    pub id: String,
+    // Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub size: u64,
+}
+
+pub fn autogen_dir() -> PathBuf {
+    home_dir().join(".config/hypr/configs/autogen")
+}
+
+fn backups_dir() -> PathBuf {
+    autogen_dir().join(".backups")
+}
+
+// Only plain file names are accepted so nothing outside the autogen directory
+// can be written or restored
+fn check_file_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.starts_with('.') || name.contains('/') || name.contains('\\') {
+        return Err(format!("Invalid config file name: {}", name));
+    }
+    Ok(())
+}
+
+// Atomically replaces `name` in the autogen directory with `content`, backing
+// up the previous version first, and returns the file's path
+pub fn write_autogen_file(name: &str, content: &str) -> Result<PathBuf, String> {
+    check_file_name(name)?;
+
+    let dir = autogen_dir();
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+
+    let path = dir.join(name);
+    if let Ok(previous) = fs::read_to_string(&path) {
+        if previous == content {
+            return Ok(path);
+        }
+        backup(name, &previous)?;
+    }
+
+    write_atomically(&path, content)?;
+    Ok(path)
+}
+
+// Writes `content` to a hidden temporary file next to `path` and renames it
+// over `path`, so readers see either the old or the new file, never half of one
+pub fn write_atomically(path: &Path, content: &str) -> Result<(), String> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| format!("Invalid path: {}", path.display()))?;
+
+    let temp_path = dir.join(format!(".{}.tmp", name));
+    let mut temp_file = fs::File::create(&temp_path)
+        .map_err(|e| format!("Failed to create {}: {}", temp_path.display(), e))?;
+    temp_file
+        .write_all(content.as_bytes())
+        .and_then(|_| temp_file.sync_all())
+        .map_err(|e| format!("Failed to write {}: {}", temp_path.display(), e))?;
+
+    fs::rename(&temp_path, path)
+        .map_err(|e| format!("Failed to move {} into place: {}", path.display(), e))
+}
+
+fn backup(name: &str, content: &str) -> Result<(), String> {
+    let dir = backups_dir();
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    fs::write(dir.join(format!("{}.{}", name, timestamp)), content)
+        .map_err(|e| format!("Failed to back up {}: {}", name, e))?;
+
+    for old in list_backups(Some(name))?.into_iter().skip(MAX_BACKUPS) {
+        let _ = fs::remove_file(dir.join(&old.id));
+    }
+
+    Ok(())
+}
+
+// Backups, newest first, optionally only those of one file
+fn list_backups(file: Option<&str>) -> Result<Vec<ConfigBackup>, String> {
+    let entries = match fs::read_dir(backups_dir()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read backup directory: {}", e)),
+    };
+
+    let mut backups: Vec<ConfigBackup> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let id = entry.file_name().to_string_lossy().to_string();
+            let (name, timestamp) = id.rsplit_once('.')?;
+            let timestamp = timestamp.parse().ok()?;
+            if file.is_some_and(|f| f != name) {
+                return None;
+            }
+
+            Some(ConfigBackup {
+                file: name.to_string(),
+                id: id.clone(),
+                timestamp,
+                size: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            })
+        })
+        .collect();
+
+    backups.sort_by_key(|b| std::cmp::Reverse(b.timestamp));
+    Ok(backups)
+}
+
+#[tauri::command]
+pub fn list_config_backups(file: Option<String>) -> Result<Vec<ConfigBackup>, String> {
+    list_backups(file.as_deref())
+}
+
+// Restores a backup; the current version is itself backed up so this can be undone
+#[tauri::command]
+pub fn restore_config_backup(file: String, id: String) -> Result<String, String> {
+    check_file_name(&file)?;
+
+    let backup = list_backups(Some(&file))?
+        .into_iter()
+        .find(|b| b.id == id)
+        .ok_or_else(|| format!("No backup {} for {}", id, file))?;
+
+    let content = fs::read_to_string(backups_dir().join(&backup.id))
+        .map_err(|e| format!("Failed to read backup {}: {}", backup.id, e))?;
+    let path = write_autogen_file(&file, &content)?;
+
+    Ok(format!("Restored {} from backup", path.display()))
+}
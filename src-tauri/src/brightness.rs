@@ -0,0 +1,390 @@
+// Brightness for internal panels through /sys/class/backlight, and for
+// external monitors through DDC/CI (VCP feature 0x10) on the connector's I2C bus.
+use crate::{read_hyprland_monitors, Monitor};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::process::Command;
+use std::thread::sleep;
+use std::time::Duration;
+
+const BACKLIGHT_ROOT: &str = "/sys/class/backlight";
+const DRM_ROOT: &str = "/sys/class/drm";
+
+// Connector types of built-in panels
+const INTERNAL_CONNECTORS: [&str; 3] = ["eDP", "LVDS", "DSI"];
+
+// linux/i2c-dev.h
+const I2C_SLAVE: u64 = 0x0703;
+// Monitors answer DDC/CI at 0x37 (0x6E/0x6F as 8-bit write/read addresses)
+const DDC_ADDRESS: u8 = 0x37;
+const DDC_DEST_ADDRESS: u8 = 0x6E;
+// Checksums of replies include the host's virtual source address
+const DDC_HOST_ADDRESS: u8 = 0x50;
+const DDC_SOURCE_ADDRESS: u8 = 0x51;
+const VCP_BRIGHTNESS: u8 = 0x10;
+// The MCCS spec asks hosts to wait this long between a request and its reply
+const DDC_REPLY_DELAY: Duration = Duration::from_millis(40);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrightnessKind {
+    Backlight,
+    Ddc,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BrightnessDevice {
+    // Monitor name as in get_monitors
+    pub monitor: String,
+    pub kind: BrightnessKind,
+    // Backlight name (e.g. "intel_backlight") or I2C device (e.g. "/dev/i2c-5")
+    pub device: String,
+    pub brightness: u32,
+    #[serde(rename = "maxBrightness")]
+    pub max_brightness: u32,
+    pub percent: u32,
+}
+
+impl BrightnessDevice {
+    fn new(monitor: &str, kind: BrightnessKind, device: String, brightness: u32, max_brightness: u32) -> Self {
+        Self {
+            monitor: monitor.to_string(),
+            kind,
+            device,
+            brightness,
+            max_brightness,
+            percent: to_percent(brightness, max_brightness),
+        }
+    }
+}
+
+fn to_percent(value: u32, max: u32) -> u32 {
+    if max == 0 {
+        return 0;
+    }
+    ((value as f64 * 100.0 / max as f64).round() as u32).min(100)
+}
+
+fn from_percent(percent: u32, max: u32) -> u32 {
+    (percent.min(100) as f64 * max as f64 / 100.0).round() as u32
+}
+
+pub fn is_internal_connector(name: &str) -> bool {
+    INTERNAL_CONNECTORS.iter().any(|prefix| name.starts_with(prefix))
+}
+
+fn read_number(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+// Backlight devices under `root` (normally /sys/class/backlight)
+pub fn list_backlights(root: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(root)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.path().join("max_brightness").exists())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+// Current and maximum brightness; `actual_brightness` is what the hardware
+// really shows, `brightness` only what was last requested
+pub fn read_backlight(root: &Path, name: &str) -> Option<(u32, u32)> {
+    let dir = root.join(name);
+    let max = read_number(&dir.join("max_brightness"))?;
+    let current = read_number(&dir.join("actual_brightness")).or_else(|| read_number(&dir.join("brightness")))?;
+    Some((current, max))
+}
+
+pub fn write_backlight(root: &Path, name: &str, value: u32) -> Result<(), String> {
+    let path = root.join(name).join("brightness");
+    match fs::write(&path, value.to_string()) {
+        Ok(()) => Ok(()),
+        // Without a udev rule only root may write to sysfs; brightnessctl
+        // goes through logind instead
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            let output = Command::new("brightnessctl")
+                .args(["--device", name, "set", &value.to_string()])
+                .output()
+                .map_err(|_| format!("No permission to write {} and brightnessctl is not installed", path.display()))?;
+            if !output.status.success() {
+                return Err(format!(
+                    "brightnessctl failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+            Ok(())
+        }
+        Err(e) => Err(format!("Failed to write {}: {}", path.display(), e)),
+    }
+}
+
+// The connector a backlight belongs to, when the driver links it to one
+// (`device` points at e.g. .../card1-eDP-1 for i915 and nouveau)
+pub fn backlight_connector(root: &Path, name: &str) -> Option<String> {
+    let device = fs::canonicalize(root.join(name).join("device")).ok()?;
+    let file_name = device.file_name()?.to_string_lossy().to_string();
+    file_name
+        .strip_prefix("card")
+        .and_then(|rest| rest.split_once('-'))
+        .map(|(_, connector)| connector.to_string())
+}
+
+// The backlight of an internal panel: the one linked to its connector, or
+// the only/first one when the driver doesn't say (amdgpu_bl*, acpi_video*)
+pub fn find_backlight(root: &Path, connector: &str) -> Option<String> {
+    let backlights = list_backlights(root);
+    backlights
+        .iter()
+        .find(|name| backlight_connector(root, name).as_deref() == Some(connector))
+        .or_else(|| {
+            // Firmware interfaces are the least reliable, so prefer the GPU's own
+            backlights
+                .iter()
+                .filter(|name| backlight_connector(root, name).is_none())
+                .min_by_key(|name| name.starts_with("acpi_video"))
+        })
+        .cloned()
+}
+
+// The I2C bus number of a connector's DDC channel, from the `ddc` link or the
+// `i2c-N` directory in its DRM sysfs entry
+pub fn ddc_bus(drm_root: &Path, connector: &str) -> Option<u32> {
+    let entries = fs::read_dir(drm_root).ok()?;
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let matches = file_name
+            .strip_prefix("card")
+            .and_then(|rest| rest.split_once('-'))
+            .is_some_and(|(_, name)| name == connector);
+        if !matches {
+            continue;
+        }
+
+        if let Ok(target) = fs::read_link(entry.path().join("ddc")) {
+            if let Some(bus) = target
+                .file_name()
+                .and_then(|n| n.to_string_lossy().strip_prefix("i2c-").and_then(|b| b.parse().ok()))
+            {
+                return Some(bus);
+            }
+        }
+
+        let bus = fs::read_dir(entry.path()).ok()?.flatten().find_map(|child| {
+            child
+                .file_name()
+                .to_string_lossy()
+                .strip_prefix("i2c-")
+                .and_then(|b| b.parse().ok())
+        });
+        if bus.is_some() {
+            return bus;
+        }
+    }
+
+    None
+}
+
+fn ddc_checksum(initial: u8, bytes: &[u8]) -> u8 {
+    bytes.iter().fold(initial, |sum, b| sum ^ b)
+}
+
+// "Get VCP Feature" request for `code`
+pub fn ddc_get_vcp_request(code: u8) -> Vec<u8> {
+    let mut packet = vec![DDC_SOURCE_ADDRESS, 0x82, 0x01, code];
+    packet.push(ddc_checksum(DDC_DEST_ADDRESS, &packet));
+    packet
+}
+
+// "Set VCP Feature" request setting `code` to `value`
+pub fn ddc_set_vcp_request(code: u8, value: u16) -> Vec<u8> {
+    let [high, low] = value.to_be_bytes();
+    let mut packet = vec![DDC_SOURCE_ADDRESS, 0x84, 0x03, code, high, low];
+    packet.push(ddc_checksum(DDC_DEST_ADDRESS, &packet));
+    packet
+}
+
+// Decodes a "Get VCP Feature" reply into (current, maximum)
+pub fn parse_vcp_reply(reply: &[u8], code: u8) -> Result<(u16, u16), String> {
+    // Source address, length, opcode, result, code, type, max (2), current (2), checksum
+    if reply.len() < 11 {
+        return Err("Short DDC/CI reply".to_string());
+    }
+    if reply[0] != DDC_DEST_ADDRESS || reply[1] & 0x7F != 8 || reply[2] != 0x02 {
+        return Err("Unexpected DDC/CI reply".to_string());
+    }
+    if ddc_checksum(DDC_HOST_ADDRESS, &reply[..10]) != reply[10] {
+        return Err("DDC/CI reply checksum mismatch".to_string());
+    }
+    if reply[3] != 0 {
+        return Err(format!("Monitor does not support VCP feature {:#04x}", code));
+    }
+    if reply[4] != code {
+        return Err("DDC/CI reply is for a different feature".to_string());
+    }
+
+    let max = u16::from_be_bytes([reply[6], reply[7]]);
+    let current = u16::from_be_bytes([reply[8], reply[9]]);
+    Ok((current, max))
+}
+
+fn open_ddc(bus: u32) -> Result<File, String> {
+    let path = format!("/dev/i2c-{}", bus);
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open {} (is i2c-dev loaded and are you in the i2c group?): {}", path, e))?;
+
+    // SAFETY: plain ioctl on a file descriptor we own, with an integer argument
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), I2C_SLAVE as _, DDC_ADDRESS as libc::c_ulong) };
+    if result < 0 {
+        return Err(format!(
+            "Failed to address the monitor on {}: {}",
+            path,
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    Ok(file)
+}
+
+fn ddc_get_brightness(bus: u32) -> Result<(u16, u16), String> {
+    let mut file = open_ddc(bus)?;
+    file.write_all(&ddc_get_vcp_request(VCP_BRIGHTNESS))
+        .map_err(|e| format!("DDC/CI request failed: {}", e))?;
+    sleep(DDC_REPLY_DELAY);
+
+    let mut reply = [0u8; 11];
+    file.read_exact(&mut reply)
+        .map_err(|e| format!("DDC/CI reply failed: {}", e))?;
+    parse_vcp_reply(&reply, VCP_BRIGHTNESS)
+}
+
+fn ddc_set_brightness(bus: u32, value: u16) -> Result<(), String> {
+    let mut file = open_ddc(bus)?;
+    file.write_all(&ddc_set_vcp_request(VCP_BRIGHTNESS, value))
+        .map_err(|e| format!("DDC/CI request failed: {}", e))?;
+    // Give the monitor time to process it before the next request
+    sleep(DDC_REPLY_DELAY);
+    Ok(())
+}
+
+fn device_for(monitor: &Monitor) -> Result<BrightnessDevice, String> {
+    if is_internal_connector(&monitor.name) {
+        let root = Path::new(BACKLIGHT_ROOT);
+        let name = find_backlight(root, &monitor.name)
+            .ok_or_else(|| format!("No backlight found for {}", monitor.name))?;
+        let (current, max) = read_backlight(root, &name)
+            .ok_or_else(|| format!("Failed to read backlight {}", name))?;
+        return Ok(BrightnessDevice::new(&monitor.name, BrightnessKind::Backlight, name, current, max));
+    }
+
+    let bus = ddc_bus(Path::new(DRM_ROOT), &monitor.name)
+        .ok_or_else(|| format!("No DDC channel found for {}", monitor.name))?;
+    let (current, max) = ddc_get_brightness(bus)?;
+    Ok(BrightnessDevice::new(
+        &monitor.name,
+        BrightnessKind::Ddc,
+        format!("/dev/i2c-{}", bus),
+        current as u32,
+        max as u32,
+    ))
+}
+
+// Brightness of every enabled monitor that can be controlled; monitors
+// without a backlight or DDC/CI support are left out
+#[tauri::command]
+pub fn get_brightness() -> Result<Vec<BrightnessDevice>, String> {
+    Ok(read_hyprland_monitors(false)?
+        .iter()
+        .filter_map(|monitor| device_for(monitor).ok())
+        .collect())
+}
+
+#[tauri::command]
+pub fn set_brightness(monitor: String, percent: u32) -> Result<BrightnessDevice, String> {
+    let target = read_hyprland_monitors(false)?
+        .into_iter()
+        .find(|m| m.name == monitor)
+        .ok_or_else(|| format!("No monitor named {}", monitor))?;
+    let device = device_for(&target)?;
+
+    match device.kind {
+        BrightnessKind::Backlight => {
+            // Never switch the panel fully off, there would be no way to see it
+            let value = from_percent(percent, device.max_brightness).max(1);
+            write_backlight(Path::new(BACKLIGHT_ROOT), &device.device, value)?;
+        }
+        BrightnessKind::Ddc => {
+            let bus = ddc_bus(Path::new(DRM_ROOT), &monitor)
+                .ok_or_else(|| format!("No DDC channel found for {}", monitor))?;
+            let value = from_percent(percent, device.max_brightness);
+            ddc_set_brightness(bus, value as u16)?;
+        }
+    }
+
+    device_for(&target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // A /sys/class/backlight layout: intel_backlight linked to card1-eDP-1,
+    // and a firmware acpi_video0 linked to no connector
+    fn sysfs(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("archion-backlight-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let connector = root.join("devices/card1-eDP-1");
+        fs::create_dir_all(&connector).unwrap();
+        for (backlight, max, current) in [("intel_backlight", "96000", "48000"), ("acpi_video0", "15", "7")] {
+            let dir = root.join("class").join(backlight);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("max_brightness"), format!("{}\n", max)).unwrap();
+            fs::write(dir.join("actual_brightness"), format!("{}\n", current)).unwrap();
+            fs::write(dir.join("brightness"), format!("{}\n", current)).unwrap();
+        }
+        std::os::unix::fs::symlink(&connector, root.join("class/intel_backlight/device")).unwrap();
+        root
+    }
+
+    #[test]
+    fn reads_backlights() {
+        let root = sysfs("read");
+        let class = root.join("class");
+
+        assert_eq!(list_backlights(&class), ["acpi_video0", "intel_backlight"]);
+        assert_eq!(read_backlight(&class, "intel_backlight"), Some((48000, 96000)));
+        assert_eq!(backlight_connector(&class, "intel_backlight").as_deref(), Some("eDP-1"));
+        assert_eq!(find_backlight(&class, "eDP-1").as_deref(), Some("intel_backlight"));
+        // A panel the GPU driver doesn't link falls back to the firmware backlight
+        assert_eq!(find_backlight(&class, "eDP-2").as_deref(), Some("acpi_video0"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn writes_backlight() {
+        let root = sysfs("write");
+        let class = root.join("class");
+
+        write_backlight(&class, "intel_backlight", from_percent(25, 96000)).unwrap();
+        assert_eq!(fs::read_to_string(class.join("intel_backlight/brightness")).unwrap(), "24000");
+        // actual_brightness is the hardware's answer, which sysfs updates itself
+        fs::write(class.join("intel_backlight/actual_brightness"), "24000\n").unwrap();
+        let (current, max) = read_backlight(&class, "intel_backlight").unwrap();
+        assert_eq!(to_percent(current, max), 25);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,388 @@
+// Decoder for the EDID blobs the kernel exposes at /sys/class/drm/<card>-<connector>/edid,
+// used to list the modes a panel really supports when Hyprland doesn't report them.
+use crate::modes::{hz_to_mhz, DisplayMode};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+const EDID_HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+const BLOCK_SIZE: usize = 128;
+const CTA_EXTENSION_TAG: u8 = 0x02;
+
+// Established timings I and II (bytes 35 and 36, most significant bit first)
+const ESTABLISHED_TIMINGS: [(i32, i32, f64); 16] = [
+    (720, 400, 70.0),
+    (720, 400, 88.0),
+    (640, 480, 60.0),
+    (640, 480, 67.0),
+    (640, 480, 72.0),
+    (640, 480, 75.0),
+    (800, 600, 56.0),
+    (800, 600, 60.0),
+    (800, 600, 72.0),
+    (800, 600, 75.0),
+    (832, 624, 75.0),
+    (1024, 768, 87.0),
+    (1024, 768, 60.0),
+    (1024, 768, 70.0),
+    (1024, 768, 75.0),
+    (1280, 1024, 75.0),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EdidInfo {
+    // Three letter PNP ID, e.g. "DEL"
+    pub manufacturer: String,
+    #[serde(rename = "productCode")]
+    pub product_code: u16,
+    #[serde(rename = "serialNumber")]
+    pub serial_number: u32,
+    #[serde(rename = "productName")]
+    pub product_name: Option<String>,
+    #[serde(rename = "serialString")]
+    pub serial_string: Option<String>,
+    #[serde(rename = "widthMm")]
+    pub width_mm: u32,
+    #[serde(rename = "heightMm")]
+    pub height_mm: u32,
+    #[serde(rename = "preferredMode")]
+    pub preferred_mode: Option<DisplayMode>,
+    // Every mode listed in the EDID, largest and fastest first
+    pub modes: Vec<DisplayMode>,
+}
+
+pub fn parse_edid(data: &[u8]) -> Result<EdidInfo, String> {
+    if data.len() < BLOCK_SIZE {
+        return Err(format!("EDID is too short ({} bytes)", data.len()));
+    }
+    if data[..8] != EDID_HEADER {
+        return Err("Missing EDID header".to_string());
+    }
+    if data[..BLOCK_SIZE].iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+        return Err("EDID checksum mismatch".to_string());
+    }
+
+    let mut info = EdidInfo {
+        manufacturer: decode_manufacturer(data[8], data[9]),
+        product_code: u16::from_le_bytes([data[10], data[11]]),
+        serial_number: u32::from_le_bytes([data[12], data[13], data[14], data[15]]),
+        product_name: None,
+        serial_string: None,
+        // The base block only stores the size in centimeters
+        width_mm: data[21] as u32 * 10,
+        height_mm: data[22] as u32 * 10,
+        preferred_mode: None,
+        modes: Vec::new(),
+    };
+
+    // Four 18 byte descriptors; the first detailed timing is the preferred mode.
+    // Interlaced timings are left out, monitor rules can't select them.
+    let mut first_timing = true;
+    for offset in [54, 72, 90, 108] {
+        let descriptor = &data[offset..offset + 18];
+
+        if let Some(timing) = parse_detailed_timing(descriptor) {
+            if first_timing {
+                first_timing = false;
+                if !timing.interlaced {
+                    info.preferred_mode = Some(timing.mode);
+                }
+                // Detailed timings carry the size in millimeters, which is more precise
+                if timing.width_mm > 0 && timing.height_mm > 0 {
+                    info.width_mm = timing.width_mm;
+                    info.height_mm = timing.height_mm;
+                }
+            }
+            if !timing.interlaced {
+                info.modes.push(timing.mode);
+            }
+            continue;
+        }
+
+        match descriptor[3] {
+            0xFC => info.product_name = Some(descriptor_text(descriptor)),
+            0xFF => info.serial_string = Some(descriptor_text(descriptor)),
+            _ => {}
+        }
+    }
+
+    let established = u16::from_be_bytes([data[35], data[36]]);
+    for (bit, (width, height, refresh)) in ESTABLISHED_TIMINGS.iter().enumerate() {
+        if established & (0x8000 >> bit) != 0 {
+            info.modes.push(DisplayMode {
+                width: *width,
+                height: *height,
+                refresh_mhz: hz_to_mhz(*refresh),
+            });
+        }
+    }
+
+    for pair in data[38..54].chunks(2) {
+        if let Some(mode) = parse_standard_timing(pair[0], pair[1]) {
+            info.modes.push(mode);
+        }
+    }
+
+    // CTA-861 extension blocks list further detailed timings (common on TVs and HDMI monitors)
+    let extensions = (data[126] as usize).min(data.len() / BLOCK_SIZE - 1);
+    for block in data[BLOCK_SIZE..].chunks_exact(BLOCK_SIZE).take(extensions) {
+        if block[0] != CTA_EXTENSION_TAG {
+            continue;
+        }
+
+        let mut offset = block[2] as usize;
+        if offset < 4 {
+            continue;
+        }
+        // The last byte of the block is its checksum
+        while offset + 18 < BLOCK_SIZE {
+            match parse_detailed_timing(&block[offset..offset + 18]) {
+                Some(timing) if timing.interlaced => {}
+                Some(timing) => info.modes.push(timing.mode),
+                None => break,
+            }
+            offset += 18;
+        }
+    }
+
+    info.modes.sort_by(|a, b| {
+        (b.width * b.height)
+            .cmp(&(a.width * a.height))
+            .then(b.refresh_mhz.cmp(&a.refresh_mhz))
+    });
+    info.modes.dedup();
+
+    Ok(info)
+}
+
+// Packed as three 5-bit letters, 1 = 'A'
+fn decode_manufacturer(high: u8, low: u8) -> String {
+    let packed = u16::from_be_bytes([high, low]);
+    [(packed >> 10) & 0x1F, (packed >> 5) & 0x1F, packed & 0x1F]
+        .iter()
+        .map(|c| match *c {
+            1..=26 => (b'A' + *c as u8 - 1) as char,
+            _ => '?',
+        })
+        .collect()
+}
+
+struct DetailedTiming {
+    mode: DisplayMode,
+    // Image size in millimeters
+    width_mm: u32,
+    height_mm: u32,
+    interlaced: bool,
+}
+
+// Returns None if the descriptor holds something other than a timing
+fn parse_detailed_timing(d: &[u8]) -> Option<DetailedTiming> {
+    let pixel_clock_khz = u16::from_le_bytes([d[0], d[1]]) as u64 * 10;
+    if pixel_clock_khz == 0 {
+        return None;
+    }
+
+    let h_active = d[2] as u64 | ((d[4] as u64 & 0xF0) << 4);
+    let h_blank = d[3] as u64 | ((d[4] as u64 & 0x0F) << 8);
+    let v_active = d[5] as u64 | ((d[7] as u64 & 0xF0) << 4);
+    let v_blank = d[6] as u64 | ((d[7] as u64 & 0x0F) << 8);
+    let width_mm = d[12] as u32 | ((d[14] as u32 & 0xF0) << 4);
+    let height_mm = d[13] as u32 | ((d[14] as u32 & 0x0F) << 8);
+    let interlaced = d[17] & 0x80 != 0;
+
+    let total = (h_active + h_blank) * (v_active + v_blank);
+    if h_active == 0 || v_active == 0 || total == 0 {
+        return None;
+    }
+
+    // Interlaced timings describe a single field, so the vertical totals are
+    // per field and the rate below is already the field rate (60 for 1080i60)
+    let height = if interlaced { v_active * 2 } else { v_active };
+    let refresh_mhz = pixel_clock_khz * 1_000_000 / total;
+
+    let mode = DisplayMode {
+        width: h_active as i32,
+        height: height as i32,
+        refresh_mhz: refresh_mhz as u32,
+    };
+    Some(DetailedTiming {
+        mode,
+        width_mm,
+        height_mm,
+        interlaced,
+    })
+}
+
+fn parse_standard_timing(first: u8, second: u8) -> Option<DisplayMode> {
+    // 0x01 0x01 marks an unused slot
+    if (first == 0x01 && second == 0x01) || first == 0x00 {
+        return None;
+    }
+
+    let width = (first as i32 + 31) * 8;
+    let height = match second >> 6 {
+        0 => width * 10 / 16,
+        1 => width * 3 / 4,
+        2 => width * 4 / 5,
+        _ => width * 9 / 16,
+    };
+    let refresh = (second & 0x3F) as f64 + 60.0;
+
+    Some(DisplayMode {
+        width,
+        height,
+        refresh_mhz: hz_to_mhz(refresh),
+    })
+}
+
+// Text of a display descriptor: up to 13 bytes, ended by a newline
+fn descriptor_text(d: &[u8]) -> String {
+    d[5..18]
+        .iter()
+        .take_while(|b| **b != 0x0A)
+        .map(|b| *b as char)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+// Reads the EDID for a connector such as `DP-2` from a DRM sysfs tree
+// (normally /sys/class/drm), where it lives under `card<N>-<connector>`
+pub fn read_connector_edid(drm_root: &Path, connector: &str) -> Option<Vec<u8>> {
+    let entries = fs::read_dir(drm_root).ok()?;
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let matches = file_name
+            .strip_prefix("card")
+            .and_then(|rest| rest.split_once('-'))
+            .map(|(_, name)| name == connector)
+            .unwrap_or(false);
+
+        if matches {
+            // Disconnected outputs expose an empty file
+            if let Ok(data) = fs::read(entry.path().join("edid")) {
+                if !data.is_empty() {
+                    return Some(data);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+pub fn connector_edid(connector: &str) -> Result<EdidInfo, String> {
+    let data = read_connector_edid(Path::new("/sys/class/drm"), connector)
+        .ok_or_else(|| format!("No EDID available for {}", connector))?;
+    parse_edid(&data)
+}
+
+#[tauri::command]
+pub fn get_monitor_edid(name: String) -> Result<EdidInfo, String> {
+    connector_edid(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Base block with a 1920x1080@60 detailed timing, name and serial
+    // descriptors, plus a CTA-861 extension with 1080i60 (interlaced) and 720p60
+    const EDID: [u8; 256] = [
+        0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x10, 0xac, 0xb1, 0xa0, 0x39, 0x30, 0x00, 0x00,
+        0x01, 0x1e, 0x01, 0x04, 0xb5, 0x3c, 0x22, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x21, 0x08, 0x00, 0xd1, 0xc0, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+        0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x02, 0x3a, 0x80, 0x18, 0x71, 0x38, 0x2d, 0x40, 0x58, 0x2c,
+        0x45, 0x00, 0x56, 0x50, 0x21, 0x00, 0x00, 0x1e, 0x00, 0x00, 0x00, 0xfc, 0x00, 0x54, 0x45, 0x53,
+        0x54, 0x20, 0x4d, 0x4f, 0x4e, 0x49, 0x54, 0x4f, 0x52, 0x0a, 0x00, 0x00, 0x00, 0xff, 0x00, 0x53,
+        0x4e, 0x30, 0x30, 0x30, 0x31, 0x0a, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x00, 0x00, 0x00, 0x10,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x2f,
+        0x02, 0x03, 0x04, 0x00, 0x01, 0x1d, 0x80, 0x18, 0x71, 0x1c, 0x16, 0x20, 0x58, 0x2c, 0x25, 0x00,
+        0xc4, 0x8e, 0x21, 0x00, 0x00, 0x9e, 0x01, 0x1d, 0x00, 0x72, 0x51, 0xd0, 0x1e, 0x20, 0x6e, 0x28,
+        0x55, 0x00, 0xc4, 0x8e, 0x21, 0x00, 0x00, 0x1e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x59,
+    ];
+
+    fn has_mode(info: &EdidInfo, width: i32, height: i32, refresh_hz: u32) -> bool {
+        info.modes
+            .iter()
+            .any(|m| m.width == width && m.height == height && (m.refresh_mhz + 500) / 1000 == refresh_hz)
+    }
+
+    #[test]
+    fn parses_base_block() {
+        let info = parse_edid(&EDID[..BLOCK_SIZE]).unwrap();
+
+        assert_eq!(info.manufacturer, "DEL");
+        assert_eq!(info.product_code, 0xA0B1);
+        assert_eq!(info.serial_number, 12345);
+        assert_eq!(info.product_name.as_deref(), Some("TEST MONITOR"));
+        assert_eq!(info.serial_string.as_deref(), Some("SN0001"));
+        assert_eq!((info.width_mm, info.height_mm), (598, 336));
+        assert!(has_mode(&info, 640, 480, 60));
+        assert!(has_mode(&info, 800, 600, 60));
+        assert!(has_mode(&info, 1024, 768, 60));
+    }
+
+    #[test]
+    fn progressive_detailed_timing() {
+        let info = parse_edid(&EDID[..BLOCK_SIZE]).unwrap();
+        let preferred = info.preferred_mode.unwrap();
+
+        assert_eq!((preferred.width, preferred.height), (1920, 1080));
+        assert_eq!(preferred.refresh_mhz, 60000);
+        assert_eq!(info.modes[0], preferred);
+    }
+
+    #[test]
+    fn interlaced_detailed_timing_reports_field_rate() {
+        let timing = parse_detailed_timing(&EDID[BLOCK_SIZE + 4..BLOCK_SIZE + 22]).unwrap();
+        let mode = timing.mode;
+
+        assert!(timing.interlaced);
+        assert_eq!((mode.width, mode.height), (1920, 1080));
+        // 74.25 MHz / (2200 * 562.5) is 60 Hz; the field totals give 60.05
+        assert!((60000..60100).contains(&mode.refresh_mhz), "{}", mode.refresh_mhz);
+    }
+
+    #[test]
+    fn interlaced_timings_are_not_listed() {
+        // The 1080i60 timing from the extension as the base block's first descriptor
+        let mut data = EDID;
+        data.copy_within(BLOCK_SIZE + 4..BLOCK_SIZE + 22, 54);
+        data[BLOCK_SIZE - 1] = 0u8.wrapping_sub(data[..BLOCK_SIZE - 1].iter().fold(0u8, |sum, b| sum.wrapping_add(*b)));
+        let info = parse_edid(&data[..BLOCK_SIZE]).unwrap();
+
+        assert_eq!(info.preferred_mode, None);
+        // 1920x1080@60 is still there from the standard timings, not 60.05 from the fields
+        assert!(!info.modes.iter().any(|m| m.width == 1920 && m.height == 1080 && m.refresh_mhz != 60000));
+        // The image size still comes from the first detailed timing
+        assert_eq!((info.width_mm, info.height_mm), (708, 398));
+    }
+
+    #[test]
+    fn cta_extension_timings() {
+        let base = parse_edid(&EDID[..BLOCK_SIZE]).unwrap();
+        let info = parse_edid(&EDID).unwrap();
+
+        assert!(!has_mode(&base, 1280, 720, 60));
+        assert!(has_mode(&info, 1280, 720, 60));
+        assert!(has_mode(&info, 1920, 1080, 60));
+        // Only the progressive 1080p60; the extension's 1080i60 is left out
+        let full_hd: Vec<_> = info.modes.iter().filter(|m| m.width == 1920 && m.height == 1080).collect();
+        assert_eq!(full_hd.len(), 1);
+        assert_eq!(full_hd[0].refresh_mhz, 60000);
+    }
+
+    #[test]
+    fn rejects_bad_checksum() {
+        let mut data = EDID;
+        data[20] ^= 0xFF;
+        assert!(parse_edid(&data).is_err());
+    }
+}
@@ -0,0 +1,288 @@
+// GPUs as the kernel's DRM subsystem lists them under /sys/class/drm, the
+// connectors each one drives, and the AQ_DRM_DEVICES order Hyprland (through
+// aquamarine) uses to pick the primary GPU, kept in the generated env.conf.
+// cardN numbers can change between boots, so the order refers to the GPUs by
+// their /dev/dri/by-path links.
+use crate::autogen;
+use crate::hypr_sources::strip_comment;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DRM_ROOT: &str = "/sys/class/drm";
+const DRI_DIR: &str = "/dev/dri";
+const DRI_BY_PATH_DIR: &str = "/dev/dri/by-path";
+// Autogen subdirectory with colon-free links to the by-path entries
+const GPU_LINKS_DIR: &str = "gpus";
+const ENV_CONFIG: &str = "env.conf";
+const DRM_DEVICES_VAR: &str = "AQ_DRM_DEVICES";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GpuConnector {
+    // Connector name as Hyprland uses it, e.g. "eDP-1" or "HDMI-A-1"
+    pub name: String,
+    // "connected", "disconnected" or "unknown"
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Gpu {
+    // DRM card name, e.g. "card1"
+    pub card: String,
+    #[serde(rename = "devicePath")]
+    pub device_path: String,
+    // Kernel driver, e.g. "i915", "amdgpu" or "nvidia"
+    pub driver: String,
+    pub vendor: String,
+    #[serde(rename = "pciSlot")]
+    pub pci_slot: Option<String>,
+    // The GPU the firmware booted on, usually the integrated one
+    #[serde(rename = "bootVga")]
+    pub boot_vga: bool,
+    pub connectors: Vec<GpuConnector>,
+}
+
+fn vendor_name(vendor_id: &str) -> String {
+    match vendor_id.trim().to_lowercase().as_str() {
+        "0x8086" => "Intel".to_string(),
+        "0x1002" => "AMD".to_string(),
+        "0x10de" => "NVIDIA".to_string(),
+        "0x1af4" => "Virtio".to_string(),
+        "0x15ad" => "VMware".to_string(),
+        other => other.to_string(),
+    }
+}
+
+// `cardN` entries of a DRM sysfs tree (normally /sys/class/drm), sorted by number
+pub fn list_gpus(drm_root: &Path) -> Vec<Gpu> {
+    let Ok(entries) = fs::read_dir(drm_root) else {
+        return Vec::new();
+    };
+    let names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+
+    let mut cards: Vec<&String> = names
+        .iter()
+        .filter(|name| name.strip_prefix("card").is_some_and(|n| n.parse::<u32>().is_ok()))
+        .collect();
+    cards.sort_by_key(|name| name[4..].parse::<u32>().unwrap_or(0));
+
+    cards
+        .into_iter()
+        .map(|card| {
+            let device = drm_root.join(card).join("device");
+            let driver = fs::read_link(device.join("driver"))
+                .ok()
+                .and_then(|d| d.file_name().map(|n| n.to_string_lossy().to_string()))
+                .unwrap_or_default();
+            let vendor = fs::read_to_string(device.join("vendor"))
+                .map(|v| vendor_name(&v))
+                .unwrap_or_default();
+            let pci_slot = fs::canonicalize(&device)
+                .ok()
+                .and_then(|d| d.file_name().map(|n| n.to_string_lossy().to_string()));
+            let boot_vga = fs::read_to_string(device.join("boot_vga"))
+                .map(|v| v.trim() == "1")
+                .unwrap_or(false);
+
+            let prefix = format!("{}-", card);
+            let mut connectors: Vec<GpuConnector> = names
+                .iter()
+                .filter_map(|name| {
+                    let connector = name.strip_prefix(&prefix)?;
+                    let status = fs::read_to_string(drm_root.join(name).join("status"))
+                        .map(|s| s.trim().to_string())
+                        .unwrap_or_else(|_| "unknown".to_string());
+                    Some(GpuConnector {
+                        name: connector.to_string(),
+                        status,
+                    })
+                })
+                .collect();
+            connectors.sort_by(|a, b| a.name.cmp(&b.name));
+
+            Gpu {
+                card: card.clone(),
+                device_path: format!("{}/{}", DRI_DIR, card),
+                driver,
+                vendor,
+                pci_slot,
+                boot_vga,
+                connectors,
+            }
+        })
+        .collect()
+}
+
+// The card that drives a connector such as "DP-2"
+pub fn connector_card(drm_root: &Path, connector: &str) -> Option<String> {
+    list_gpus(drm_root)
+        .into_iter()
+        .find(|gpu| gpu.connectors.iter().any(|c| c.name == connector))
+        .map(|gpu| gpu.card)
+}
+
+pub fn monitor_gpu(connector: &str) -> Option<String> {
+    connector_card(Path::new(DRM_ROOT), connector)
+}
+
+// The card a device path points at, following by-path and other links
+fn device_card(path: &str) -> String {
+    let path = Path::new(path);
+    fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+// Cards listed in an `env = AQ_DRM_DEVICES,...` line
+pub fn parse_drm_devices(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = strip_comment(line);
+            let (key, value) = line.split_once('=')?;
+            if key.trim() != "env" {
+                return None;
+            }
+            let (name, value) = value.split_once(',')?;
+            (name.trim() == DRM_DEVICES_VAR).then(|| value.trim().to_string())
+        })
+        .next_back()
+        .map(|value| {
+            value
+                .split(':')
+                .filter(|path| !path.is_empty())
+                .map(device_card)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// The /dev/dri/by-path entry (`pci-0000:01:00.0-card`) that points at `card`
+fn by_path_link(card: &str) -> Option<PathBuf> {
+    let target = Path::new(DRI_DIR).join(card);
+    fs::read_dir(DRI_BY_PATH_DIR)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.to_string_lossy().ends_with("-card"))
+        .find(|path| fs::canonicalize(path).is_ok_and(|p| p == target))
+}
+
+// Path to put in AQ_DRM_DEVICES for `card`. The variable is split on ':',
+// which by-path names contain, so a link without colons in the autogen
+// directory stands in for the by-path entry. Cards without one keep their
+// /dev/dri/cardN path.
+fn stable_device_path(card: &str) -> Result<String, String> {
+    let Some(link) = by_path_link(card) else {
+        return Ok(format!("{}/{}", DRI_DIR, card));
+    };
+
+    let dir = autogen::autogen_dir().join(GPU_LINKS_DIR);
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let name = link.file_name().map(|n| n.to_string_lossy().replace(':', "-")).unwrap_or_default();
+    let alias = dir.join(name);
+    if fs::read_link(&alias).is_ok_and(|target| target == link) {
+        return Ok(alias.display().to_string());
+    }
+    let _ = fs::remove_file(&alias);
+    std::os::unix::fs::symlink(&link, &alias)
+        .map_err(|e| format!("Failed to link {} to {}: {}", alias.display(), link.display(), e))?;
+    Ok(alias.display().to_string())
+}
+
+// Replaces the AQ_DRM_DEVICES line of env.conf, keeping any other variables
+pub fn format_env_config(content: &str, devices: &[String]) -> String {
+    let mut config_content = String::from(autogen::AUTOGEN_HEADER);
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let is_drm_devices = trimmed
+            .split_once('=')
+            .and_then(|(_, value)| value.split_once(','))
+            .is_some_and(|(name, _)| name.trim() == DRM_DEVICES_VAR);
+        if !is_drm_devices {
+            config_content.push_str(trimmed);
+            config_content.push('\n');
+        }
+    }
+
+    if !devices.is_empty() {
+        config_content.push_str(&format!("env = {},{}\n", DRM_DEVICES_VAR, devices.join(":")));
+    }
+
+    config_content
+}
+
+fn read_env_config() -> String {
+    fs::read_to_string(autogen::autogen_dir().join(ENV_CONFIG)).unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_gpus() -> Vec<Gpu> {
+    list_gpus(Path::new(DRM_ROOT))
+}
+
+// Cards in the order saved in env.conf; empty when Hyprland picks on its own
+#[tauri::command]
+pub fn get_gpu_order() -> Vec<String> {
+    parse_drm_devices(&read_env_config())
+}
+
+// Saves the GPU order, primary first. Hyprland only reads it when it starts,
+// so this takes effect on the next login.
+#[tauri::command]
+pub fn set_gpu_order(cards: Vec<String>) -> Result<String, String> {
+    let gpus = get_gpus();
+    for (i, card) in cards.iter().enumerate() {
+        if !gpus.iter().any(|gpu| &gpu.card == card) {
+            return Err(format!("No GPU named {}", card));
+        }
+        if cards[..i].contains(card) {
+            return Err(format!("{} is listed more than once", card));
+        }
+    }
+
+    let devices = cards
+        .iter()
+        .map(|card| stable_device_path(card))
+        .collect::<Result<Vec<String>, String>>()?;
+    let path = autogen::write_autogen_file(ENV_CONFIG, &format_env_config(&read_env_config(), &devices))?;
+
+    if cards.is_empty() {
+        Ok(format!("Removed the GPU order from {}, restart Hyprland to apply", path.display()))
+    } else {
+        Ok(format!("GPU order saved to {}, restart Hyprland to apply", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_cards_through_device_links() {
+        let dir = std::env::temp_dir().join(format!("archion-gpu-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("card1"), "").unwrap();
+        let link = dir.join("pci-0000-01-00.0-card");
+        std::os::unix::fs::symlink(dir.join("card1"), &link).unwrap();
+
+        let content = format!("env = AQ_DRM_DEVICES,{}:/dev/dri/card7 # dGPU first\n", link.display());
+        assert_eq!(parse_drm_devices(&content), ["card1", "card7"]);
+
+        let config = format_env_config(&content, &[link.display().to_string()]);
+        assert!(config.ends_with(&format!("env = AQ_DRM_DEVICES,{}\n", link.display())));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
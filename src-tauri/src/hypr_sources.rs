@@ -0,0 +1,387 @@
+// Follows the `source = ...` chain starting at hyprland.conf to find out which
+// of the generated autogen files Hyprland actually loads, and adds the missing
+// include when they aren't.
+use crate::autogen::{self, autogen_dir};
+use crate::home_dir;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Guards against runaway include chains; Hyprland itself refuses deeper nesting
+const MAX_SOURCE_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutogenSourceStatus {
+    pub file: String,
+    pub path: String,
+    pub loaded: bool,
+    // Config file whose `source` line loads it
+    #[serde(rename = "sourcedFrom")]
+    pub sourced_from: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceReport {
+    #[serde(rename = "mainConfig")]
+    pub main_config: String,
+    #[serde(rename = "mainConfigExists")]
+    pub main_config_exists: bool,
+    // Every file reached from the main config, in load order
+    #[serde(rename = "loadedFiles")]
+    pub loaded_files: Vec<String>,
+    #[serde(rename = "autogenFiles")]
+    pub autogen_files: Vec<AutogenSourceStatus>,
+}
+
+// $XDG_CONFIG_HOME/hypr, where Hyprland and its tools look for their configs
+pub fn hypr_config_dir() -> PathBuf {
+    let config_home = std::env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| home_dir().join(".config"));
+    config_home.join("hypr")
+}
+
+pub fn main_config_path() -> PathBuf {
+    hypr_config_dir().join("hyprland.conf")
+}
+
+// Loaded file -> the file that sourced it
+struct SourceChain {
+    order: Vec<PathBuf>,
+    sourced_from: HashMap<PathBuf, PathBuf>,
+    variables: HashMap<String, String>,
+}
+
+impl SourceChain {
+    fn follow(main_config: &Path) -> Self {
+        let mut chain = SourceChain {
+            order: Vec::new(),
+            sourced_from: HashMap::new(),
+            variables: HashMap::new(),
+        };
+        chain.visit(main_config, 0);
+        chain
+    }
+
+    fn visit(&mut self, file: &Path, depth: usize) {
+        let file = canonical(file);
+        if depth > MAX_SOURCE_DEPTH || self.order.contains(&file) {
+            return;
+        }
+        let Ok(content) = fs::read_to_string(&file) else {
+            return;
+        };
+        self.order.push(file.clone());
+
+        let base_dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        for line in content.lines() {
+            let line = strip_comment(line);
+            let Some((key, value)) = line.trim().split_once('=') else {
+                continue;
+            };
+            let key = key.trim();
+            let value = value.trim();
+
+            if let Some(name) = key.strip_prefix('$') {
+                self.variables.insert(name.to_string(), value.to_string());
+                continue;
+            }
+            if key != "source" {
+                continue;
+            }
+
+            let pattern = self.expand(value, &base_dir);
+            for path in expand_glob(&pattern) {
+                let path = canonical(&path);
+                self.sourced_from.entry(path.clone()).or_insert_with(|| file.clone());
+                self.visit(&path, depth + 1);
+            }
+        }
+    }
+
+    // Resolves `~`, `$HOME`, `$variables` and paths relative to the including file
+    fn expand(&self, value: &str, base_dir: &Path) -> PathBuf {
+        let mut expanded = value.to_string();
+
+        // Longest names first so `$dir` doesn't eat the start of `$dir_extra`
+        let mut names: Vec<&String> = self.variables.keys().collect();
+        names.sort_by_key(|n| std::cmp::Reverse(n.len()));
+        for name in names {
+            expanded = expanded.replace(&format!("${}", name), &self.variables[name]);
+        }
+
+        expanded = expanded.replace("$HOME", &home_dir().display().to_string());
+        if let Some(rest) = expanded.strip_prefix("~/") {
+            expanded = format!("{}/{}", home_dir().display(), rest);
+        }
+
+        let path = PathBuf::from(expanded);
+        if path.is_absolute() {
+            path
+        } else {
+            base_dir.join(path)
+        }
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+// Hyprland treats `#` as a comment unless it is doubled (`##` is a literal `#`)
+pub fn strip_comment(line: &str) -> String {
+    let mut result = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '#' {
+            if chars.peek() == Some(&'#') {
+                chars.next();
+                result.push('#');
+                continue;
+            }
+            break;
+        }
+        result.push(c);
+    }
+    result
+}
+
+// Doubles every `#` so a value written to a config isn't cut off as a comment
+pub fn escape_comment(value: &str) -> String {
+    value.replace('#', "##")
+}
+
+// Expands `*` and `?` wildcards component by component. Like glob(3),
+// wildcards don't match hidden entries.
+fn expand_glob(pattern: &Path) -> Vec<PathBuf> {
+    let mut candidates = vec![PathBuf::from("/")];
+
+    for component in pattern.components().skip(1) {
+        let part = component.as_os_str().to_string_lossy().to_string();
+        let mut next = Vec::new();
+
+        for base in &candidates {
+            if !part.contains('*') && !part.contains('?') {
+                let path = base.join(&part);
+                if path.exists() {
+                    next.push(path);
+                }
+                continue;
+            }
+
+            let Ok(entries) = fs::read_dir(base) else {
+                continue;
+            };
+            let mut matched: Vec<PathBuf> = entries
+                .flatten()
+                .filter(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    !name.starts_with('.') && wildcard_match(&part, &name)
+                })
+                .map(|entry| entry.path())
+                .collect();
+            matched.sort();
+            next.extend(matched);
+        }
+
+        candidates = next;
+    }
+
+    candidates
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Classic two-pointer matching with backtracking to the last `*`
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn autogen_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(autogen_dir())
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                    !name.starts_with('.') && name.ends_with(".conf") && path.is_file()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+fn build_report() -> SourceReport {
+    let main_config = main_config_path();
+    let chain = SourceChain::follow(&main_config);
+
+    let autogen_files = autogen_files()
+        .into_iter()
+        .map(|path| {
+            let canonical_path = canonical(&path);
+            AutogenSourceStatus {
+                file: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+                path: path.display().to_string(),
+                loaded: chain.order.contains(&canonical_path),
+                sourced_from: chain.sourced_from.get(&canonical_path).map(|p| p.display().to_string()),
+            }
+        })
+        .collect();
+
+    SourceReport {
+        main_config: main_config.display().to_string(),
+        main_config_exists: main_config.exists(),
+        loaded_files: chain.order.iter().map(|p| p.display().to_string()).collect(),
+        autogen_files,
+    }
+}
+
+#[tauri::command]
+pub fn check_autogen_sources() -> SourceReport {
+    build_report()
+}
+
+// Appends `lines` to the config at `path`, backing it up first. A config
+// that exists but can't be read is left alone rather than replaced.
+fn append_lines(path: &Path, lines: &[String]) -> Result<(), String> {
+    let existing = match fs::read_to_string(path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+
+    if existing.is_some() {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let backup = path.with_file_name(format!("hyprland.conf.archion-backup-{}", timestamp));
+        fs::copy(path, &backup)
+            .map_err(|e| format!("Failed to back up {}: {}", path.display(), e))?;
+    } else if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+
+    let mut content = existing.unwrap_or_default();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push('\n');
+    content.push_str(&lines.join("\n"));
+    content.push('\n');
+
+    autogen::write_atomically(path, &content)
+}
+
+// Adds `source` lines for the autogen files hyprland.conf doesn't load yet.
+// When none are loaded a single glob covers them and any added later;
+// otherwise only the missing files are added, so nothing gets loaded twice.
+#[tauri::command]
+pub fn repair_autogen_source() -> Result<SourceReport, String> {
+    let report = build_report();
+    let missing: Vec<&AutogenSourceStatus> = report.autogen_files.iter().filter(|f| !f.loaded).collect();
+    if missing.is_empty() {
+        return Ok(report);
+    }
+
+    let autogen_dir_in_config = "~/.config/hypr/configs/autogen";
+    let mut lines = vec!["# Added by Archion Settings to load its generated configuration".to_string()];
+    if missing.len() == report.autogen_files.len() {
+        lines.push(format!("source = {}/*.conf", autogen_dir_in_config));
+    } else {
+        for file in &missing {
+            lines.push(format!("source = {}/{}", autogen_dir_in_config, file.file));
+        }
+    }
+
+    // Write through symlinks (dotfile managers) instead of replacing them
+    append_lines(&canonical(&main_config_path()), &lines)?;
+
+    Ok(build_report())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("archion-sources-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn source_lines() -> Vec<String> {
+        vec!["source = ~/.config/hypr/configs/autogen/*.conf".to_string()]
+    }
+
+    #[test]
+    fn appends_to_existing_config() {
+        let dir = temp_dir("append");
+        let config = dir.join("hyprland.conf");
+        fs::write(&config, "monitor = , preferred, auto, 1").unwrap();
+
+        append_lines(&config, &source_lines()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&config).unwrap(),
+            "monitor = , preferred, auto, 1\n\nsource = ~/.config/hypr/configs/autogen/*.conf\n"
+        );
+        // The config and its backup
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn creates_missing_config() {
+        let dir = temp_dir("create");
+        let config = dir.join("hypr/hyprland.conf");
+
+        append_lines(&config, &source_lines()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&config).unwrap(),
+            "\nsource = ~/.config/hypr/configs/autogen/*.conf\n"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn leaves_unreadable_config_alone() {
+        let dir = temp_dir("unreadable");
+        let config = dir.join("hyprland.conf");
+        let original = b"# \xff\xfe not UTF-8\nmonitor = , preferred, auto, 1\n".to_vec();
+        fs::write(&config, &original).unwrap();
+
+        assert!(append_lines(&config, &source_lines()).is_err());
+
+        assert_eq!(fs::read(&config).unwrap(), original);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,317 @@
+// Minimal client for Hyprland's request socket (.socket.sock), used instead of
+// spawning hyprctl for every query.
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Hyprland separates the replies of a [[BATCH]] request with this delimiter
+const BATCH_DELIMITER: &str = "\n\n\n";
+
+#[derive(Debug)]
+pub enum HyprlandError {
+    // HYPRLAND_INSTANCE_SIGNATURE is not set, so we are not inside a Hyprland session
+    NotRunning,
+    // The instance signature is set but the socket does not exist
+    SocketMissing(PathBuf),
+    Io(std::io::Error),
+    // Hyprland answered, but rejected the request
+    Command(String),
+    // The reply could not be decoded
+    InvalidResponse(String),
+}
+
+impl fmt::Display for HyprlandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HyprlandError::NotRunning => write!(f, "Hyprland is not running (HYPRLAND_INSTANCE_SIGNATURE is not set)"),
+            HyprlandError::SocketMissing(path) => write!(f, "Hyprland socket not found at {}", path.display()),
+            HyprlandError::Io(e) => write!(f, "Hyprland socket error: {}", e),
+            HyprlandError::Command(reply) => write!(f, "Hyprland rejected the request: {}", reply),
+            HyprlandError::InvalidResponse(e) => write!(f, "Invalid reply from Hyprland: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HyprlandError {}
+
+impl From<std::io::Error> for HyprlandError {
+    fn from(e: std::io::Error) -> Self {
+        HyprlandError::Io(e)
+    }
+}
+
+impl From<HyprlandError> for String {
+    fn from(e: HyprlandError) -> Self {
+        e.to_string()
+    }
+}
+
+pub type HyprlandResult<T> = Result<T, HyprlandError>;
+
+// Directory holding the sockets of the running Hyprland instance
+pub fn instance_dir() -> HyprlandResult<PathBuf> {
+    let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+        .map_err(|_| HyprlandError::NotRunning)?;
+
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("/tmp"));
+
+    let dir = runtime_dir.join("hypr").join(&signature);
+    if dir.exists() {
+        return Ok(dir);
+    }
+
+    // Hyprland releases before 0.40 kept their sockets under /tmp
+    let legacy_dir = Path::new("/tmp/hypr").join(&signature);
+    if legacy_dir.exists() {
+        return Ok(legacy_dir);
+    }
+
+    Ok(dir)
+}
+
+pub struct HyprlandClient {
+    socket_path: PathBuf,
+}
+
+impl HyprlandClient {
+    pub fn new(socket_path: impl Into<PathBuf>) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+        }
+    }
+
+    // Connects to the instance named by the current environment
+    pub fn from_env() -> HyprlandResult<Self> {
+        Ok(Self::new(instance_dir()?.join(".socket.sock")))
+    }
+
+    // Sends a raw request such as `monitors` or `keyword monitor ...` and returns the reply
+    pub fn request(&self, command: &str) -> HyprlandResult<String> {
+        let mut stream = UnixStream::connect(&self.socket_path).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                HyprlandError::SocketMissing(self.socket_path.clone())
+            } else {
+                HyprlandError::Io(e)
+            }
+        })?;
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+
+        stream.write_all(command.as_bytes())?;
+        stream.shutdown(std::net::Shutdown::Write)?;
+
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply)?;
+
+        String::from_utf8(reply).map_err(|e| HyprlandError::InvalidResponse(e.to_string()))
+    }
+
+    // Sends a request with the `j/` prefix and decodes the JSON reply
+    pub fn json<T: DeserializeOwned>(&self, command: &str) -> HyprlandResult<T> {
+        let reply = self.request(&format!("j/{}", command))?;
+        serde_json::from_str(&reply).map_err(|_| {
+            // Unknown requests come back as plain text instead of JSON
+            HyprlandError::Command(reply.trim().to_string())
+        })
+    }
+
+    // Sends several requests in one round trip and returns one reply per request
+    pub fn batch<S: AsRef<str>>(&self, commands: &[S]) -> HyprlandResult<Vec<String>> {
+        if commands.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let joined = commands
+            .iter()
+            .map(|c| c.as_ref())
+            .collect::<Vec<_>>()
+            .join(";");
+        let reply = self.request(&format!("[[BATCH]]{}", joined))?;
+
+        Ok(reply.split(BATCH_DELIMITER).map(|r| r.to_string()).collect())
+    }
+
+    // Sends a request that answers `ok` on success, such as hyprpaper's `wallpaper`
+    pub fn command(&self, command: &str) -> HyprlandResult<()> {
+        expect_ok(&self.request(command)?)
+    }
+
+    // Sends several `ok`-answering requests (e.g. `keyword`), failing on the first rejected one
+    pub fn batch_commands<S: AsRef<str>>(&self, commands: &[S]) -> HyprlandResult<()> {
+        for reply in self.batch(commands)? {
+            expect_ok(&reply)?;
+        }
+        Ok(())
+    }
+}
+
+fn expect_ok(reply: &str) -> HyprlandResult<()> {
+    let reply = reply.trim();
+    if reply.eq_ignore_ascii_case("ok") {
+        Ok(())
+    } else {
+        Err(HyprlandError::Command(reply.to_string()))
+    }
+}
+
+// Socket that Hyprland broadcasts events on (.socket2.sock)
+pub fn event_socket_path() -> HyprlandResult<PathBuf> {
+    Ok(instance_dir()?.join(".socket2.sock"))
+}
+
+// Events from the event socket that the frontend cares about. Hyprland sends
+// several of these in both a v1 and a v2 form; only one of each is parsed so
+// the frontend does not receive duplicates.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum HyprlandEvent {
+    MonitorAdded {
+        id: Option<i64>,
+        name: String,
+        description: Option<String>,
+    },
+    MonitorRemoved {
+        name: String,
+    },
+    Workspace {
+        id: Option<i64>,
+        name: String,
+    },
+    FocusedMonitor {
+        monitor: String,
+        workspace: String,
+    },
+    ActiveWindow {
+        class: String,
+        title: String,
+    },
+    ConfigReloaded,
+}
+
+impl HyprlandEvent {
+    // Parses one `EVENT>>DATA` line from the event socket
+    pub fn parse(line: &str) -> Option<Self> {
+        let (event, data) = line.split_once(">>")?;
+
+        match event {
+            "monitoraddedv2" => {
+                let mut parts = data.splitn(3, ',');
+                let id = parts.next()?.parse().ok();
+                let name = parts.next()?.to_string();
+                let description = parts.next().map(|d| d.to_string());
+                Some(HyprlandEvent::MonitorAdded { id, name, description })
+            }
+            "monitorremoved" => Some(HyprlandEvent::MonitorRemoved {
+                name: data.to_string(),
+            }),
+            "workspacev2" => {
+                let (id, name) = data.split_once(',')?;
+                Some(HyprlandEvent::Workspace {
+                    id: id.parse().ok(),
+                    name: name.to_string(),
+                })
+            }
+            "focusedmon" => {
+                let (monitor, workspace) = data.split_once(',')?;
+                Some(HyprlandEvent::FocusedMonitor {
+                    monitor: monitor.to_string(),
+                    workspace: workspace.to_string(),
+                })
+            }
+            "activewindow" => {
+                // Window titles may contain commas, the class never does
+                let (class, title) = data.split_once(',').unwrap_or((data, ""));
+                Some(HyprlandEvent::ActiveWindow {
+                    class: class.to_string(),
+                    title: title.to_string(),
+                })
+            }
+            "configreloaded" => Some(HyprlandEvent::ConfigReloaded),
+            _ => None,
+        }
+    }
+
+    // Name of the Tauri event this is re-emitted as
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            HyprlandEvent::MonitorAdded { .. } => "monitor-added",
+            HyprlandEvent::MonitorRemoved { .. } => "monitor-removed",
+            HyprlandEvent::Workspace { .. } => "workspace-changed",
+            HyprlandEvent::FocusedMonitor { .. } => "focused-monitor-changed",
+            HyprlandEvent::ActiveWindow { .. } => "active-window-changed",
+            HyprlandEvent::ConfigReloaded => "config-reloaded",
+        }
+    }
+}
+
+// Stand-in for a request socket (Hyprland's, hyprpaper's, ...): answers each
+// connection with the next reply and hands back the requests it received
+#[cfg(test)]
+pub(crate) fn mock_socket(name: &str, replies: &[&'static str]) -> (PathBuf, std::thread::JoinHandle<Vec<String>>) {
+    let socket = std::env::temp_dir().join(format!("archion-{}-{}.sock", name, std::process::id()));
+    let _ = std::fs::remove_file(&socket);
+    let listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+
+    let replies = replies.to_vec();
+    let server = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for reply in replies {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            stream.read_to_string(&mut request).unwrap();
+            stream.write_all(reply.as_bytes()).unwrap();
+            requests.push(request);
+        }
+        requests
+    });
+    (socket, server)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_sends_one_request_and_splits_replies() {
+        let (socket, server) = mock_socket("hyprland-batch", &["ok\n\n\n[{\"id\":1}]\n\n\nok"]);
+        let client = HyprlandClient::new(&socket);
+
+        let replies = client
+            .batch(&["keyword monitor DP-1,preferred,auto,1", "j/workspaces", "dispatch workspace 2"])
+            .unwrap();
+
+        assert_eq!(replies, ["ok", "[{\"id\":1}]", "ok"]);
+        assert_eq!(
+            server.join().unwrap(),
+            ["[[BATCH]]keyword monitor DP-1,preferred,auto,1;j/workspaces;dispatch workspace 2"]
+        );
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[test]
+    fn batch_commands_reports_rejected_request() {
+        let (socket, server) = mock_socket("hyprland-reject", &["ok\n\n\ninvalid field monitor: bad rule"]);
+        let client = HyprlandClient::new(&socket);
+
+        let error = client.batch_commands(&["keyword a 1", "keyword monitor bad"]).unwrap_err();
+
+        assert!(matches!(&error, HyprlandError::Command(reply) if reply == "invalid field monitor: bad rule"));
+        server.join().unwrap();
+        let _ = std::fs::remove_file(&socket);
+    }
+
+    #[test]
+    fn missing_socket_is_reported() {
+        let socket = std::env::temp_dir().join("archion-hyprland-missing.sock");
+        let error = HyprlandClient::new(&socket).request("monitors").unwrap_err();
+        assert!(matches!(error, HyprlandError::SocketMissing(path) if path == socket));
+    }
+}
@@ -0,0 +1,369 @@
+// ICC color profiles: discovery in the usual profile directories, a header
+// and tag reader for the description and white point, and assignment to a
+// monitor through the `icc` monitor rule argument.
+use crate::monitor_config::write_monitor_config;
+use crate::{apply_monitors_live, home_dir, Monitor};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const HEADER_SIZE: usize = 128;
+const PROFILE_SIGNATURE: &[u8; 4] = b"acsp";
+// Signature, offset and size of one tag table entry
+const TAG_ENTRY_SIZE: usize = 12;
+const MAX_SCAN_DEPTH: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IccProfile {
+    pub path: String,
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    pub description: Option<String>,
+    // e.g. "2.1" or "4.3"
+    pub version: String,
+    // Profile class, "mntr" for display profiles
+    #[serde(rename = "deviceClass")]
+    pub device_class: String,
+    #[serde(rename = "colorSpace")]
+    pub color_space: String,
+    // Media white point as CIE XYZ, and as xy chromaticity
+    #[serde(rename = "whitePoint")]
+    pub white_point: Option<[f64; 3]>,
+    #[serde(rename = "whitePointXy")]
+    pub white_point_xy: Option<[f64; 2]>,
+}
+
+fn profile_dirs() -> Vec<PathBuf> {
+    vec![
+        home_dir().join(".local/share/icc"),
+        home_dir().join(".color/icc"),
+        PathBuf::from("/usr/local/share/color/icc"),
+        PathBuf::from("/usr/share/color/icc"),
+    ]
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn signature(data: &[u8], offset: usize) -> String {
+    data.get(offset..offset + 4)
+        .map(|s| String::from_utf8_lossy(s).trim_end().to_string())
+        .unwrap_or_default()
+}
+
+// The data of the tag with signature `tag`, from the tag table after the header.
+// The declared tag count is capped at what the file can hold, so a corrupt
+// count can't make this loop billions of times.
+fn find_tag<'a>(data: &'a [u8], tag: &[u8; 4]) -> Option<&'a [u8]> {
+    let max_count = data.len().saturating_sub(HEADER_SIZE + 4) / TAG_ENTRY_SIZE;
+    let count = (be_u32(data, HEADER_SIZE)? as usize).min(max_count);
+    (0..count).find_map(|i| {
+        let entry = HEADER_SIZE + 4 + i * TAG_ENTRY_SIZE;
+        if data.get(entry..entry + 4)? != tag {
+            return None;
+        }
+        let offset = be_u32(data, entry + 4)? as usize;
+        let size = be_u32(data, entry + 8)? as usize;
+        data.get(offset..offset.checked_add(size)?)
+    })
+}
+
+// v2 profiles use `desc` (textDescriptionType), v4 profiles `mluc`
+// (multiLocalizedUnicodeType), of which the English entry is preferred
+fn parse_description(tag: &[u8]) -> Option<String> {
+    let text = match tag.get(0..4)? {
+        b"desc" => {
+            let length = be_u32(tag, 8)? as usize;
+            let ascii = tag.get(12..12 + length)?;
+            String::from_utf8_lossy(ascii).trim_end_matches('\0').to_string()
+        }
+        b"mluc" => {
+            let count = be_u32(tag, 8)? as usize;
+            let record_size = be_u32(tag, 12)? as usize;
+            let records: Vec<(&[u8], usize, usize)> = (0..count)
+                .filter_map(|i| {
+                    let record = 16 + i * record_size;
+                    Some((
+                        tag.get(record..record + 2)?,
+                        be_u32(tag, record + 4)? as usize,
+                        be_u32(tag, record + 8)? as usize,
+                    ))
+                })
+                .collect();
+            let (_, length, offset) = records
+                .iter()
+                .find(|(language, _, _)| *language == b"en")
+                .or(records.first())?;
+
+            let utf16: Vec<u16> = tag
+                .get(*offset..offset + length)?
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&utf16).trim_end_matches('\0').to_string()
+        }
+        _ => return None,
+    };
+
+    let text = text.trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+// XYZType: three s15Fixed16Number values after the type signature
+fn parse_xyz(tag: &[u8]) -> Option<[f64; 3]> {
+    if tag.get(0..4)? != b"XYZ " {
+        return None;
+    }
+    let value = |i: usize| be_u32(tag, 8 + i * 4).map(|v| v as i32 as f64 / 65536.0);
+    Some([value(0)?, value(1)?, value(2)?])
+}
+
+// s15Fixed16ArrayType holding the 3x3 `chad` matrix, row by row
+fn parse_matrix(tag: &[u8]) -> Option<[[f64; 3]; 3]> {
+    if tag.get(0..4)? != b"sf32" {
+        return None;
+    }
+    let value = |i: usize| be_u32(tag, 8 + i * 4).map(|v| v as i32 as f64 / 65536.0);
+    Some([
+        [value(0)?, value(1)?, value(2)?],
+        [value(3)?, value(4)?, value(5)?],
+        [value(6)?, value(7)?, value(8)?],
+    ])
+}
+
+fn invert(m: [[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let cofactor = |r: usize, c: usize| {
+        let (r1, r2) = ((r + 1) % 3, (r + 2) % 3);
+        let (c1, c2) = ((c + 1) % 3, (c + 2) % 3);
+        m[r1][c1] * m[r2][c2] - m[r1][c2] * m[r2][c1]
+    };
+    let determinant = (0..3).map(|c| m[0][c] * cofactor(0, c)).sum::<f64>();
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+
+    let mut inverse = [[0.0; 3]; 3];
+    for (r, row) in inverse.iter_mut().enumerate() {
+        for (c, value) in row.iter_mut().enumerate() {
+            *value = cofactor(c, r) / determinant;
+        }
+    }
+    Some(inverse)
+}
+
+// The display's actual white. v2 profiles store it in `wtpt`; from v4 on
+// `wtpt` is always the D50 PCS white, and the original white is recovered by
+// undoing the `chad` adaptation on the header's PCS illuminant.
+fn media_white_point(data: &[u8]) -> Option<[f64; 3]> {
+    if data[8] < 4 {
+        return find_tag(data, b"wtpt").and_then(parse_xyz);
+    }
+
+    let Some(chad) = find_tag(data, b"chad").and_then(parse_matrix) else {
+        // No adaptation was needed, so the white was D50 to begin with
+        return find_tag(data, b"wtpt").and_then(parse_xyz);
+    };
+    let inverse = invert(chad)?;
+    let value = |i: usize| be_u32(data, 68 + i * 4).map(|v| v as i32 as f64 / 65536.0);
+    let illuminant = [value(0)?, value(1)?, value(2)?];
+
+    Some(inverse.map(|row| row.iter().zip(illuminant).map(|(a, b)| a * b).sum()))
+}
+
+pub fn parse_icc_profile(data: &[u8]) -> Result<IccProfile, String> {
+    if data.len() < HEADER_SIZE + 4 {
+        return Err("File is too short to be an ICC profile".to_string());
+    }
+    if &data[36..40] != PROFILE_SIGNATURE {
+        return Err("Not an ICC profile (missing 'acsp' signature)".to_string());
+    }
+
+    let white_point = media_white_point(data);
+    let white_point_xy = white_point.and_then(|[x, y, z]| {
+        let sum = x + y + z;
+        (sum > 0.0).then(|| [x / sum, y / sum])
+    });
+
+    Ok(IccProfile {
+        path: String::new(),
+        file_name: String::new(),
+        description: find_tag(data, b"desc").and_then(parse_description),
+        version: format!("{}.{}", data[8], data[9] >> 4),
+        device_class: signature(data, 12),
+        color_space: signature(data, 16),
+        white_point,
+        white_point_xy,
+    })
+}
+
+pub fn read_icc_profile(path: &Path) -> Result<IccProfile, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let profile = parse_icc_profile(&data).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    Ok(IccProfile {
+        path: path.display().to_string(),
+        file_name: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        ..profile
+    })
+}
+
+fn scan_profiles(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth < MAX_SCAN_DEPTH {
+                scan_profiles(&path, depth + 1, files);
+            }
+            continue;
+        }
+        let is_profile = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .is_some_and(|e| e == "icc" || e == "icm");
+        if is_profile {
+            files.push(path);
+        }
+    }
+}
+
+// Display profiles (class `mntr`) in the user and system profile directories;
+// files that don't parse are skipped
+#[tauri::command]
+pub fn list_icc_profiles() -> Vec<IccProfile> {
+    let mut files = Vec::new();
+    for dir in profile_dirs() {
+        scan_profiles(&dir, 0, &mut files);
+    }
+
+    files
+        .iter()
+        .filter_map(|path| read_icc_profile(path).ok())
+        .filter(|profile| profile.device_class == "mntr")
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_icc_profile(path: String) -> Result<IccProfile, String> {
+    read_icc_profile(Path::new(&path))
+}
+
+// Assigns a profile to a monitor (or removes it with None), applies it live
+// and saves it to monitors.conf
+#[tauri::command]
+pub fn set_monitor_icc_profile(monitor: Monitor, path: Option<String>) -> Result<String, String> {
+    if let Some(path) = &path {
+        if path.contains(',') || !Path::new(path).is_absolute() {
+            return Err(format!("Invalid profile path: {}", path));
+        }
+        let profile = read_icc_profile(Path::new(path))?;
+        if profile.color_space != "RGB" {
+            return Err(format!("{} is not an RGB profile", profile.file_name));
+        }
+    }
+
+    let monitor = Monitor {
+        icc_profile: path,
+        ..monitor
+    };
+    apply_monitors_live(std::slice::from_ref(&monitor))?;
+    write_monitor_config(std::slice::from_ref(&monitor))?;
+
+    Ok(match &monitor.icc_profile {
+        Some(path) => format!("Assigned {} to {}", path, monitor.name),
+        None => format!("Removed the color profile from {}", monitor.name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+    // Bradford adaptation from D65 to D50
+    const D65_TO_D50: [f64; 9] = [
+        1.0478112, 0.0228866, -0.0501270, 0.0295424, 0.9904844, -0.0170491, -0.0092345, 0.0150436, 0.7521316,
+    ];
+
+    fn s15(values: &[f64]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|v| ((v * 65536.0).round() as i32).to_be_bytes())
+            .collect()
+    }
+
+    fn tag(signature: &[u8; 4], values: &[f64]) -> Vec<u8> {
+        let mut data = signature.to_vec();
+        data.extend([0; 4]);
+        data.extend(s15(values));
+        data
+    }
+
+    fn profile(major_version: u8, tags: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut data = vec![0; HEADER_SIZE];
+        data[8] = major_version;
+        data[12..16].copy_from_slice(b"mntr");
+        data[16..20].copy_from_slice(b"RGB ");
+        data[36..40].copy_from_slice(PROFILE_SIGNATURE);
+        data[68..80].copy_from_slice(&s15(&D50));
+
+        data.extend((tags.len() as u32).to_be_bytes());
+        let mut offset = HEADER_SIZE + 4 + tags.len() * TAG_ENTRY_SIZE;
+        for (signature, tag) in tags {
+            data.extend(*signature);
+            data.extend((offset as u32).to_be_bytes());
+            data.extend((tag.len() as u32).to_be_bytes());
+            offset += tag.len();
+        }
+        for (_, tag) in tags {
+            data.extend(tag);
+        }
+        data
+    }
+
+    fn assert_xy(profile: &IccProfile, x: f64, y: f64) {
+        let [px, py] = profile.white_point_xy.unwrap();
+        assert!((px - x).abs() < 0.001 && (py - y).abs() < 0.001, "{} {}", px, py);
+    }
+
+    #[test]
+    fn v2_white_point_from_wtpt() {
+        let data = profile(2, &[(b"wtpt", tag(b"XYZ ", &[0.9505, 1.0, 1.089]))]);
+        let parsed = parse_icc_profile(&data).unwrap();
+        assert_eq!(parsed.version, "2.0");
+        assert_xy(&parsed, 0.3127, 0.3290);
+    }
+
+    #[test]
+    fn v4_white_point_undoes_chad() {
+        let data = profile(
+            4,
+            &[(b"wtpt", tag(b"XYZ ", &D50)), (b"chad", tag(b"sf32", &D65_TO_D50))],
+        );
+        assert_xy(&parse_icc_profile(&data).unwrap(), 0.3127, 0.3290);
+    }
+
+    #[test]
+    fn corrupt_tag_count_is_bounded() {
+        let mut data = profile(2, &[(b"wtpt", tag(b"XYZ ", &D50))]);
+        // Declares about four billion tags, then is cut off mid-table
+        data[HEADER_SIZE..HEADER_SIZE + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        data.truncate(HEADER_SIZE + 10);
+
+        let start = std::time::Instant::now();
+        let parsed = parse_icc_profile(&data).unwrap();
+        assert!(parsed.white_point.is_none() && parsed.description.is_none());
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn v4_without_chad_is_d50() {
+        let data = profile(4, &[(b"wtpt", tag(b"XYZ ", &D50))]);
+        assert_xy(&parse_icc_profile(&data).unwrap(), 0.3457, 0.3585);
+    }
+}
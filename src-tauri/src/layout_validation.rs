@@ -0,0 +1,305 @@
+// Sanity checks for a monitor layout before it is written to monitors.conf:
+// overlapping outputs, outputs the cursor can't reach, and scales Hyprland
+// would reject because the logical size isn't a whole number of pixels.
+use crate::modes::{self, Modeline};
+use crate::Monitor;
+use serde::Serialize;
+use std::path::Path;
+
+// Hyprland only accepts fractional scales in steps of 1/120 (wp_fractional_scale)
+const SCALE_STEP: f64 = 1.0 / 120.0;
+const MAX_SCALE: f64 = 10.0;
+// How far from a whole pixel a logical size may be and still count as valid
+const LOGICAL_SIZE_TOLERANCE: f64 = 0.01;
+// Largest error of a scale rounded to two decimals
+const REPORTED_SCALE_ROUNDING: f64 = 0.005 + 1e-9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LayoutIssue {
+    pub monitor: String,
+    pub severity: Severity,
+    // Stable identifier for the frontend, e.g. "overlap" or "invalid-scale"
+    pub code: String,
+    pub message: String,
+    #[serde(rename = "suggestedScale")]
+    pub suggested_scale: Option<f64>,
+}
+
+impl LayoutIssue {
+    fn new(monitor: &Monitor, severity: Severity, code: &str, message: String) -> Self {
+        Self {
+            monitor: monitor.name.clone(),
+            severity,
+            code: code.to_string(),
+            message,
+            suggested_scale: None,
+        }
+    }
+}
+
+// Area an enabled monitor covers in the global layout, in logical pixels
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Rect {
+    fn of(monitor: &Monitor) -> Self {
+        let scale = if monitor.scale > 0.0 { monitor.scale } else { 1.0 };
+        let (width, height) = logical_size(monitor.width, monitor.height, scale);
+        // Odd transforms rotate by 90 or 270 degrees
+        let (width, height) = if monitor.transform % 2 == 1 { (height, width) } else { (width, height) };
+
+        Self {
+            x: monitor.x as f64,
+            y: monitor.y as f64,
+            width,
+            height,
+        }
+    }
+
+    fn overlaps(&self, other: &Rect) -> bool {
+        let overlap_x = self.x.max(other.x) < (self.x + self.width).min(other.x + other.width);
+        let overlap_y = self.y.max(other.y) < (self.y + self.height).min(other.y + other.height);
+        overlap_x && overlap_y
+    }
+
+    // Whether the cursor can cross from one to the other: they share an edge
+    // (touching corners don't count) or overlap, which is reported separately
+    fn touches(&self, other: &Rect) -> bool {
+        if self.overlaps(other) {
+            return true;
+        }
+
+        let shared_x = self.x.max(other.x) < (self.x + self.width).min(other.x + other.width);
+        let shared_y = self.y.max(other.y) < (self.y + self.height).min(other.y + other.height);
+
+        let vertical_edge = (self.x + self.width - other.x).abs() < 1.0 || (other.x + other.width - self.x).abs() < 1.0;
+        let horizontal_edge = (self.y + self.height - other.y).abs() < 1.0 || (other.y + other.height - self.y).abs() < 1.0;
+
+        (vertical_edge && shared_y) || (horizontal_edge && shared_x)
+    }
+}
+
+fn logical_size(width: i32, height: i32, scale: f64) -> (f64, f64) {
+    (width as f64 / scale, height as f64 / scale)
+}
+
+pub fn is_valid_scale(width: i32, height: i32, scale: f64) -> bool {
+    if scale <= 0.0 {
+        return false;
+    }
+    let (logical_width, logical_height) = logical_size(width, height, scale);
+    (logical_width - logical_width.round()).abs() < LOGICAL_SIZE_TOLERANCE
+        && (logical_height - logical_height.round()).abs() < LOGICAL_SIZE_TOLERANCE
+}
+
+// The valid scale closest to `scale` for a mode, if there is one
+pub fn nearest_valid_scale(width: i32, height: i32, scale: f64) -> Option<f64> {
+    let steps = (MAX_SCALE / SCALE_STEP) as i32;
+
+    (1..=steps)
+        .map(|step| step as f64 * SCALE_STEP)
+        .filter(|candidate| is_valid_scale(width, height, *candidate))
+        .min_by(|a, b| (a - scale).abs().total_cmp(&(b - scale).abs()))
+}
+
+// hyprctl reports scales rounded to two decimals (1.33 for 4/3), which is off
+// the grid Hyprland actually uses; a reported scale is taken to mean the valid
+// scale it was rounded from
+pub fn snap_reported_scale(width: i32, height: i32, scale: f64) -> f64 {
+    if is_valid_scale(width, height, scale) {
+        return scale;
+    }
+    nearest_valid_scale(width, height, scale)
+        .filter(|valid| (valid - scale).abs() <= REPORTED_SCALE_ROUNDING)
+        .unwrap_or(scale)
+}
+
+pub fn validate_layout(monitors: &[Monitor]) -> Vec<LayoutIssue> {
+    let mut issues = Vec::new();
+
+    let enabled: Vec<&Monitor> = monitors.iter().filter(|m| !m.disabled).collect();
+    if !monitors.is_empty() && enabled.is_empty() {
+        issues.push(LayoutIssue {
+            monitor: String::new(),
+            severity: Severity::Error,
+            code: "no-enabled-monitor".to_string(),
+            message: "At least one monitor has to stay enabled".to_string(),
+            suggested_scale: None,
+        });
+    }
+
+    for (i, monitor) in monitors.iter().enumerate() {
+        if monitors[..i].iter().any(|m| m.rule_target() == monitor.rule_target()) {
+            issues.push(LayoutIssue::new(
+                monitor,
+                Severity::Error,
+                "duplicate",
+                format!("{} is configured more than once", monitor.rule_target()),
+            ));
+        }
+    }
+
+    for monitor in &enabled {
+        if monitor.width <= 0 || monitor.height <= 0 || monitor.refresh_rate <= 0.0 {
+            issues.push(LayoutIssue::new(
+                monitor,
+                Severity::Error,
+                "invalid-mode",
+                format!(
+                    "{}x{}@{:.2}Hz is not a valid mode",
+                    monitor.width, monitor.height, monitor.refresh_rate
+                ),
+            ));
+            continue;
+        }
+
+        if let Some(modeline) = &monitor.modeline {
+            if let Err(e) = Modeline::parse(modeline) {
+                issues.push(LayoutIssue::new(
+                    monitor,
+                    Severity::Error,
+                    "invalid-modeline",
+                    format!("Invalid modeline for {}: {}", monitor.name, e),
+                ));
+            }
+        } else if !monitor.modes.is_empty()
+            && modes::find_mode(&monitor.modes, monitor.width, monitor.height, monitor.refresh_rate).is_none()
+        {
+            issues.push(LayoutIssue::new(
+                monitor,
+                Severity::Warning,
+                "custom-mode",
+                format!(
+                    "{}x{}@{:.2}Hz is not advertised by {}, Hyprland will try to create it",
+                    monitor.width, monitor.height, monitor.refresh_rate, monitor.name
+                ),
+            ));
+        }
+
+        if !is_valid_scale(monitor.width, monitor.height, monitor.scale) {
+            let suggested_scale = nearest_valid_scale(monitor.width, monitor.height, monitor.scale);
+            let mut message = format!(
+                "Scale {} does not divide {}x{} into whole pixels",
+                monitor.scale, monitor.width, monitor.height
+            );
+            if let Some(suggested) = suggested_scale {
+                message.push_str(&format!(", the nearest valid scale is {:.6}", suggested));
+            }
+
+            issues.push(LayoutIssue {
+                suggested_scale,
+                ..LayoutIssue::new(monitor, Severity::Error, "invalid-scale", message)
+            });
+        }
+
+        if let Some(icc) = &monitor.icc_profile {
+            if !Path::new(icc).is_file() {
+                issues.push(LayoutIssue::new(
+                    monitor,
+                    Severity::Error,
+                    "missing-icc-profile",
+                    format!("Color profile {} for {} does not exist", icc, monitor.name),
+                ));
+            }
+        }
+
+        if let Some(mirror) = &monitor.mirror {
+            let target_enabled = enabled
+                .iter()
+                .any(|m| &m.name == mirror && m.name != monitor.name);
+            if !target_enabled {
+                issues.push(LayoutIssue::new(
+                    monitor,
+                    Severity::Error,
+                    "invalid-mirror",
+                    format!("{} mirrors {}, which is not an enabled monitor", monitor.name, mirror),
+                ));
+            }
+        }
+    }
+
+    // Mirrored outputs show another monitor's content and take no space in the layout
+    let placed: Vec<(&Monitor, Rect)> = enabled
+        .iter()
+        .filter(|m| m.mirror.is_none() && m.width > 0 && m.height > 0)
+        .map(|m| (*m, Rect::of(m)))
+        .collect();
+
+    for (i, (monitor, rect)) in placed.iter().enumerate() {
+        for (other, other_rect) in &placed[i + 1..] {
+            if rect.overlaps(other_rect) {
+                issues.push(LayoutIssue::new(
+                    monitor,
+                    Severity::Error,
+                    "overlap",
+                    format!("{} overlaps {}", monitor.name, other.name),
+                ));
+            }
+        }
+    }
+
+    // Every monitor has to be reachable from the first one by crossing shared
+    // edges, otherwise the cursor can't move between them
+    if placed.len() > 1 {
+        let mut reachable = vec![false; placed.len()];
+        let mut queue = vec![0];
+        reachable[0] = true;
+
+        while let Some(current) = queue.pop() {
+            for next in 0..placed.len() {
+                if !reachable[next] && placed[current].1.touches(&placed[next].1) {
+                    reachable[next] = true;
+                    queue.push(next);
+                }
+            }
+        }
+
+        for (i, (monitor, _)) in placed.iter().enumerate() {
+            if !reachable[i] {
+                issues.push(LayoutIssue::new(
+                    monitor,
+                    Severity::Warning,
+                    "gap",
+                    format!("{} does not touch the other monitors, the cursor can't reach it", monitor.name),
+                ));
+            }
+        }
+    }
+
+    issues
+}
+
+#[tauri::command]
+pub fn validate_monitor_layout(monitors: Vec<Monitor>) -> Vec<LayoutIssue> {
+    validate_layout(&monitors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snaps_rounded_reported_scale() {
+        let scale = snap_reported_scale(2560, 1440, 1.33);
+        assert!((scale - 4.0 / 3.0).abs() < 1e-9);
+        assert!(is_valid_scale(2560, 1440, scale));
+    }
+
+    #[test]
+    fn keeps_valid_and_distant_scales() {
+        assert_eq!(snap_reported_scale(2560, 1440, 1.5), 1.5);
+        assert_eq!(snap_reported_scale(1920, 1080, 1.1), 1.1);
+    }
+}
@@ -1,10 +1,37 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+mod autogen;
+mod brightness;
+mod edid;
+mod gpu;
+mod hypr_sources;
+mod hyprland;
+mod icc;
+mod layout_validation;
+mod modes;
+mod monitor_config;
+mod networkmanager;
+mod nightlight;
+mod profiles;
+mod virtual_outputs;
+mod wallpaper;
+mod wifi;
+mod wifi_connections;
+mod wifi_enterprise;
+mod workspaces;
+
+use hyprland::{HyprlandClient, HyprlandEvent};
+use modes::DisplayMode;
+use monitor_config::{format_monitor_rule, write_monitor_config};
 use std::process::Command;
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use tauri::{Manager, Emitter};
 
+// How long a live-applied monitor layout stays active without confirmation
+const DEFAULT_MONITOR_REVERT_SECS: u64 = 15;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Monitor {
     pub id: i32,
@@ -19,23 +46,103 @@ pub struct Monitor {
     pub scale: f64,
     #[serde(rename = "availableModes")]
     pub available_modes: Vec<String>,
+    // 0-7, rotation and flip as in Hyprland's `transform` monitor argument
+    #[serde(default)]
+    pub transform: i32,
+    // Name of the monitor this one mirrors
+    #[serde(default)]
+    pub mirror: Option<String>,
+    // 8 or 10; None leaves Hyprland's default (8)
+    #[serde(default)]
+    pub bitdepth: Option<i32>,
+    // 0 off, 1 on, 2 fullscreen only, 3 fullscreen with video/game content
+    #[serde(default)]
+    pub vrr: Option<i32>,
+    // Color management preset: auto, srgb, wide, edid, hdr or hdredid
+    #[serde(default)]
+    pub cm: Option<String>,
+    // "Make Model Serial" as reported by Hyprland, without the connector name
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub make: String,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub serial: String,
+    // Write the rule as `desc:...` so it follows the physical display to any port
+    #[serde(default, rename = "matchByDescription")]
+    pub match_by_description: bool,
+    // `availableModes` parsed, used to write the exact advertised mode
+    #[serde(default)]
+    pub modes: Vec<DisplayMode>,
+    // Custom modeline (without the `modeline` keyword) used instead of width/height/refresh
+    #[serde(default)]
+    pub modeline: Option<String>,
+    // Headless or nested Wayland output rather than a physical display
+    #[serde(default, rename = "virtual")]
+    pub is_virtual: bool,
+    // DRM card driving the output, e.g. "card1"
+    #[serde(default)]
+    pub gpu: Option<String>,
+    // ICC profile applied through the rule's `icc` argument
+    #[serde(default, rename = "iccProfile")]
+    pub icc_profile: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WifiNetwork {
-    pub ssid: String,
-    pub signal_strength: i32,
-    pub security: String,
-    pub connected: bool,
-    pub saved: bool,
-    pub in_use: bool,
+impl Monitor {
+    // What a monitor rule for this output is keyed on: the connector name, or
+    // `desc:<description>` when matching by description
+    pub fn rule_target(&self) -> String {
+        if self.match_by_description && !self.description.is_empty() {
+            format!("desc:{}", self.description)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    // Id that stays the same across calls: a hash of the description, which
+    // follows the physical display, or Hyprland's id when there is none
+    pub fn stable_id(description: &str, hyprland_id: Option<i64>, fallback: i32) -> i32 {
+        if !description.is_empty() {
+            return fnv1a(description.as_bytes());
+        }
+        hyprland_id
+            .filter(|id| *id >= 0)
+            .map(|id| id as i32)
+            .unwrap_or(fallback)
+    }
+
+    // Whether two entries describe the same display, preferring the
+    // description since connector names change between ports and docks
+    pub fn same_output(&self, other: &Monitor) -> bool {
+        if !self.description.is_empty() && !other.description.is_empty() {
+            self.description == other.description
+        } else {
+            self.name == other.name
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WifiStatus {
-    pub enabled: bool,
-    pub connected_ssid: Option<String>,
-    pub interface: String,
+// 32-bit FNV-1a, masked to stay a positive i32 (and a safe JavaScript number)
+fn fnv1a(bytes: &[u8]) -> i32 {
+    let hash = bytes
+        .iter()
+        .fold(0x811c9dc5u32, |hash, b| (hash ^ *b as u32).wrapping_mul(0x01000193));
+    (hash & 0x7fff_ffff) as i32
+}
+
+// Layout that was active before the last live apply, kept until the user
+// confirms the new one or the countdown runs out
+struct PendingMonitorApply {
+    id: u64,
+    previous: Vec<Monitor>,
+}
+
+#[derive(Default)]
+struct MonitorApplyState {
+    next_id: AtomicU64,
+    pending: Mutex<Option<PendingMonitorApply>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -114,14 +221,41 @@ pub fn run() {
                 eprintln!("Failed to start theme monitoring: {}", e);
             }
         });
+
+        // Hyprland forgets headless outputs when it restarts. Restoring them
+        // talks to Hyprland and rewrites monitors.conf, so keep it off the
+        // main thread.
+        tauri::async_runtime::spawn_blocking(|| {
+            if let Err(e) = virtual_outputs::restore_virtual_outputs() {
+                eprintln!("Failed to restore virtual outputs: {}", e);
+            }
+        });
+
+        // Forward Hyprland events (monitor hotplug, workspaces, focus) to the frontend
+        let app_handle = app.handle().clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = monitor_hyprland_events(app_handle).await {
+                eprintln!("Failed to start Hyprland event monitoring: {}", e);
+            }
+        });
+        
+        // Follow the night light schedule
+        let app_handle = app.handle().clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = nightlight::run_night_light_schedule(app_handle).await {
+                eprintln!("Failed to start night light schedule: {}", e);
+            }
+        });
         
         Ok(())
     })
+    .manage(MonitorApplyState::default())
+    .manage(nightlight::NightLightState::default())
     .plugin(tauri_plugin_opener::init())
     .plugin(tauri_plugin_dialog::init())
 
     // Invokeable commands
-    .invoke_handler(tauri::generate_handler![greet, get_monitors, save_monitor_config, get_wifi_status, get_wifi_networks, refresh_wifi_networks, connect_wifi, disconnect_wifi, forget_wifi, toggle_wifi, get_bluetooth_status, get_bluetooth_devices, toggle_bluetooth, start_bluetooth_discovery, stop_bluetooth_discovery, pair_bluetooth_device, unpair_bluetooth_device, connect_bluetooth_device, disconnect_bluetooth_device, trust_bluetooth_device, get_theme_settings, save_theme_settings, get_available_themes, get_system_theme, monitor_system_theme_changes, get_color_scheme, detect_aur_helper, get_installed_packages, search_packages, get_package_updates, install_package, remove_package, update_package, system_update])
+    .invoke_handler(tauri::generate_handler![greet, get_monitors, save_monitor_config, apply_monitor_config, confirm_monitor_config, revert_monitor_config, monitor_config::get_saved_monitors, layout_validation::validate_monitor_layout, edid::get_monitor_edid, autogen::list_config_backups, autogen::restore_config_backup, hypr_sources::check_autogen_sources, hypr_sources::repair_autogen_source, modes::parse_display_mode, modes::validate_modeline, profiles::list_monitor_profiles, profiles::save_monitor_profile, profiles::delete_monitor_profile, profiles::activate_monitor_profile, workspaces::get_workspaces, workspaces::get_workspace_rules, workspaces::save_workspace_rules, virtual_outputs::list_virtual_outputs, virtual_outputs::create_virtual_output, virtual_outputs::configure_virtual_output, virtual_outputs::remove_virtual_output, wallpaper::list_wallpapers, wallpaper::get_wallpaper_backend, wallpaper::get_wallpapers, wallpaper::set_wallpaper, nightlight::get_night_light, nightlight::set_night_light, nightlight::preview_night_light_temperature, brightness::get_brightness, brightness::set_brightness, gpu::get_gpus, gpu::get_gpu_order, gpu::set_gpu_order, icc::list_icc_profiles, icc::get_icc_profile, icc::set_monitor_icc_profile, wifi::get_wifi_status, wifi::get_wifi_networks, wifi::get_wifi_scan, wifi::refresh_wifi_networks, wifi::connect_wifi, wifi::connect_wifi_access_point, wifi::connect_hidden_wifi, wifi_enterprise::connect_enterprise_wifi, wifi_enterprise::pick_wifi_certificate, wifi::disconnect_wifi, wifi::forget_wifi, wifi_connections::list_saved_wifi_connections, wifi_connections::get_wifi_connection, wifi_connections::validate_wifi_connection, wifi_connections::save_wifi_connection, wifi::toggle_wifi, get_bluetooth_status, get_bluetooth_devices, toggle_bluetooth, start_bluetooth_discovery, stop_bluetooth_discovery, pair_bluetooth_device, unpair_bluetooth_device, connect_bluetooth_device, disconnect_bluetooth_device, trust_bluetooth_device, get_theme_settings, save_theme_settings, get_available_themes, get_system_theme, monitor_system_theme_changes, get_color_scheme, detect_aur_helper, get_installed_packages, search_packages, get_package_updates, install_package, remove_package, update_package, system_update])
 
 
     .run(tauri::generate_context!())
@@ -135,20 +269,18 @@ fn greet(name: &str) -> String {
 
 #[tauri::command]
 fn get_monitors() -> Result<Vec<Monitor>, String> {
-    let output = Command::new("hyprctl")
-        .arg("monitors")
-        .arg("-j")
-        .output()
-        .map_err(|e| format!("Failed to execute hyprctl: {}", e))?;
-    
-    if !output.status.success() {
-        return Err("Failed to get monitors from hyprctl".to_string());
-    }
-    
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let monitors: Vec<serde_json::Value> = serde_json::from_str(&stdout)
-        .map_err(|e| format!("Failed to parse monitor data: {}", e))?;
+    read_hyprland_monitors(false)
+}
+
+// Reads the current monitor state from Hyprland. With `include_disabled`,
+// disabled outputs are listed too so a snapshot can bring them back later.
+fn read_hyprland_monitors(include_disabled: bool) -> Result<Vec<Monitor>, String> {
+    let request = if include_disabled { "monitors all" } else { "monitors" };
+    let monitors: Vec<serde_json::Value> = HyprlandClient::from_env()?
+        .json(request)
+        .map_err(|e| format!("Failed to get monitors from Hyprland: {}", e))?;
     
+    let saved = monitor_config::read_monitor_config().unwrap_or_default();
     let mut result = Vec::new();
     
     for (index, monitor) in monitors.iter().enumerate() {
@@ -159,15 +291,37 @@ fn get_monitors() -> Result<Vec<Monitor>, String> {
         let y = monitor["y"].as_i64().unwrap_or(0) as i32;
         let refresh_rate = monitor["refreshRate"].as_f64().unwrap_or(60.0);
         let disabled = monitor["disabled"].as_bool().unwrap_or(false);
-        let scale = monitor["scale"].as_f64().unwrap_or(1.0);
+        let scale = layout_validation::snap_reported_scale(width, height, monitor["scale"].as_f64().unwrap_or(1.0));
+        let transform = monitor["transform"].as_i64().unwrap_or(0) as i32;
+        let mirror = monitor["mirrorOf"].as_str()
+            .filter(|m| !m.is_empty() && *m != "none")
+            .map(|m| m.to_string());
+        // Hyprland only reports the pixel format, 10 bit formats contain "2101010"
+        let bitdepth = monitor["currentFormat"].as_str()
+            .filter(|f| f.contains("2101010"))
+            .map(|_| 10);
+        let make = monitor["make"].as_str().unwrap_or("").trim().to_string();
+        let model = monitor["model"].as_str().unwrap_or("").trim().to_string();
+        let serial = monitor["serial"].as_str().unwrap_or("").trim().to_string();
+        // Some Hyprland versions append the connector, e.g. "Dell Inc. DELL U2719D 8JQ5SS2 (DP-2)"
+        let description = monitor["description"].as_str().unwrap_or("").trim();
+        let description = description
+            .strip_suffix(&format!(" ({})", name))
+            .unwrap_or(description)
+            .to_string();
         
         // Get available modes from JSON
         let available_modes = if let Some(modes_array) = monitor["availableModes"].as_array() {
             modes_array.iter()
                 .filter_map(|mode| mode.as_str().map(|s| s.to_string()))
                 .collect()
+        } else if let Ok(edid) = edid::connector_edid(&name) {
+            // Fall back to the modes the panel itself lists in its EDID
+            edid.modes.iter()
+                .map(|mode| format!("{}x{}@{:.2}Hz", mode.width, mode.height, mode.refresh_hz()))
+                .collect()
         } else {
-            // Fallback to common modes if not available
+            // Fallback to common modes if the EDID can't be read either
             vec![
                 "1920x1080@60.00Hz".to_string(),
                 "1920x1080@59.94Hz".to_string(),
@@ -189,8 +343,22 @@ fn get_monitors() -> Result<Vec<Monitor>, String> {
             ]
         };
         
+        let modes = modes::parse_modes(&available_modes);
+        let is_virtual = virtual_outputs::is_virtual_output(&name);
+        let gpu = gpu::monitor_gpu(&name);
+        // Hyprland doesn't report the profile, and only reports whether VRR is
+        // active and the resolved color preset rather than what the rule asked
+        // for. Leave them unset unless monitors.conf says otherwise, so saving
+        // doesn't pin the current state into the rule.
+        let saved_monitor = saved
+            .iter()
+            .find(|m| m.name == name || (!description.is_empty() && m.description == description));
+        let vrr = saved_monitor.and_then(|m| m.vrr);
+        let cm = saved_monitor.and_then(|m| m.cm.clone());
+        let icc_profile = saved_monitor.and_then(|m| m.icc_profile.clone());
+        
         result.push(Monitor {
-            id: index as i32,
+            id: Monitor::stable_id(&description, monitor["id"].as_i64(), index as i32),
             name,
             width,
             height,
@@ -200,281 +368,178 @@ fn get_monitors() -> Result<Vec<Monitor>, String> {
             disabled,
             scale,
             available_modes,
+            transform,
+            mirror,
+            bitdepth,
+            vrr,
+            cm,
+            description,
+            make,
+            model,
+            serial,
+            match_by_description: false,
+            modes,
+            modeline: None,
+            is_virtual,
+            gpu,
+            icc_profile,
         });
     }
+
+    // Identical displays without a serial number share a description; tell
+    // them apart by connector
+    for i in 0..result.len() {
+        if result[..i].iter().any(|m| m.id == result[i].id) {
+            let key = format!("{}|{}", result[i].description, result[i].name);
+            result[i].id = fnv1a(key.as_bytes());
+        }
+    }
     
     Ok(result)
 }
 
 
 #[tauri::command]
-fn save_monitor_config(monitors: Vec<Monitor>) -> Result<String, String> {
+fn save_monitor_config(monitors: Vec<Monitor>, force: Option<bool>) -> Result<String, String> {
     println!("Received monitors for saving: {:?}", monitors);
     
-    // Get home directory
-    let home = std::env::var("HOME")
-        .or_else(|_| std::env::var("USERPROFILE"))
-        .unwrap_or_else(|_| ".".to_string());
-    
-    // Create config directory path
-    let config_dir = Path::new(&home).join(".config/hypr/configs/autogen");
-    let config_file = config_dir.join("monitors.conf");
-    
-    // Create directory if it doesn't exist
-    fs::create_dir_all(&config_dir)
-        .map_err(|e| format!("Failed to create config directory: {}", e))?;
-    
-    // Generate configuration content
-    let mut config_content = String::new();
-    config_content.push_str("###############################################################\n");
-    config_content.push_str("## DO NOT EDIT THIS FILE!                                    ##\n");
-    config_content.push_str("## This file is automatically generated by Archion Settings. ##\n");
-    config_content.push_str("###############################################################\n");
-    
-    for monitor in &monitors {
-        config_content.push_str(&format!(
-            "monitor = {}, {}x{}@{:.2}, {}x{}, {:.2}\n",
-            monitor.name,
-            monitor.width,
-            monitor.height,
-            monitor.refresh_rate,
-            monitor.x,
-            monitor.y,
-            monitor.scale
-        ));
-
-        if monitor.disabled {
-            config_content.push_str(&format!("monitor = {}, disabled\n", monitor.name));
-        }
+    // Refuse layouts Hyprland would reject or that leave a screen unusable,
+    // unless the caller explicitly forces the save. Outputs saved earlier stay
+    // in the file, so the layout checked is the one the file will describe.
+    let merged = monitor_config::merge_monitors(monitor_config::read_monitor_config()?, &monitors);
+    let errors: Vec<String> = layout_validation::validate_layout(&merged)
+        .into_iter()
+        .filter(|issue| issue.severity == layout_validation::Severity::Error)
+        .map(|issue| issue.message)
+        .collect();
+    if !errors.is_empty() && !force.unwrap_or(false) {
+        return Err(format!("Monitor layout is invalid: {}", errors.join("; ")));
     }
     
-    println!("Generated monitor configuration:\n{}", config_content);
-    
-    // Write to config file
-    fs::write(&config_file, config_content)
-        .map_err(|e| format!("Failed to write config file: {}", e))?;
-    
-    // Also apply the configuration live using hyprctl
-    // for monitor in monitors {
-    //     if monitor.disabled {
-    //         // Disable monitor
-    //         let output = Command::new("hyprctl")
-    //             .arg("keyword")
-    //             .arg("monitor")
-    //             .arg(format!("{}, disable", monitor.name))
-    //             .output()
-    //             .map_err(|e| format!("Failed to disable monitor {}: {}", monitor.name, e))?;
-            
-    //         if !output.status.success() {
-    //             return Err(format!("Failed to disable monitor {}", monitor.name));
-    //         }
-    //     } else {
-    //         // Configure monitor
-    //         let config = format!(
-    //             "{}, {}x{}@{}, {}x{}, {}",
-    //             monitor.name,
-    //             monitor.width,
-    //             monitor.height,
-    //             monitor.refresh_rate,
-    //             monitor.x,
-    //             monitor.y,
-    //             monitor.scale
-    //         );
-            
-    //         let output = Command::new("hyprctl")
-    //             .arg("keyword")
-    //             .arg("monitor")
-    //             .arg(config)
-    //             .output()
-    //             .map_err(|e| format!("Failed to configure monitor {}: {}", monitor.name, e))?;
-            
-    //         if !output.status.success() {
-    //             return Err(format!("Failed to configure monitor {}", monitor.name));
-    //         }
-    //     }
-    // }
+    let config_file = write_monitor_config(&monitors)?;
     
     Ok(format!("Monitor configuration saved to {}", config_file.display()))
 }
 
-#[tauri::command]
-fn get_wifi_status() -> Result<WifiStatus, String> {
-    let output = Command::new("nmcli")
-        .args(["-t", "-f", "WIFI", "general"])
-        .output()
-        .map_err(|e| format!("Failed to get WiFi status: {}", e))?;
-    
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let wifi_enabled = stdout.trim() == "enabled";
-    
-    // Get WiFi interface
-    let interface_output = Command::new("nmcli")
-        .args(["-t", "-f", "DEVICE,TYPE", "device"])
-        .output()
-        .map_err(|e| format!("Failed to get interfaces: {}", e))?;
-    
-    let interface_stdout = String::from_utf8_lossy(&interface_output.stdout);
-    let mut interface = String::from("wlan0"); // default fallback
-    
-    for line in interface_stdout.lines() {
-        let parts: Vec<&str> = line.split(':').collect();
-        if parts.len() >= 2 && parts[1] == "wifi" {
-            interface = parts[0].to_string();
-            break;
-        }
-    }
-    
-    // Get connected network
-    let connected_output = Command::new("nmcli")
-        .args(["-t", "-f", "NAME", "connection", "show", "--active"])
-        .output()
-        .map_err(|e| format!("Failed to get active connections: {}", e))?;
-    
-    let connected_stdout = String::from_utf8_lossy(&connected_output.stdout);
-    let connected_ssid = if connected_stdout.trim().is_empty() {
-        None
-    } else {
-        connected_stdout.trim().lines().next().map(|s| s.to_string())
-    };
-    
-    Ok(WifiStatus {
-        enabled: wifi_enabled,
-        connected_ssid,
-        interface,
-    })
+fn home_dir() -> PathBuf {
+    let home = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home)
+}
+
+// Directory for the app's own state (profiles and the like), as opposed to
+// the Hyprland config files it generates
+fn app_config_dir() -> PathBuf {
+    home_dir().join(".config/archion-settings")
+}
+
+// Applies monitors live through `keyword monitor` requests. Enabled outputs are
+// configured before any are disabled so there is always a screen to show on.
+fn apply_monitors_live(monitors: &[Monitor]) -> Result<(), String> {
+    let enabled = monitors.iter().filter(|m| !m.disabled);
+    let disabled = monitors.iter().filter(|m| m.disabled);
+
+    let commands: Vec<String> = enabled
+        .chain(disabled)
+        .map(|monitor| {
+            if monitor.disabled {
+                format!("keyword monitor {}, disable", monitor.rule_target())
+            } else {
+                format!("keyword monitor {}", format_monitor_rule(monitor))
+            }
+        })
+        .collect();
+
+    HyprlandClient::from_env()?
+        .batch_commands(&commands)
+        .map_err(|e| format!("Failed to configure monitors: {}", e))
 }
 
 #[tauri::command]
-fn get_wifi_networks() -> Result<Vec<WifiNetwork>, String> {
-    // Skip automatic rescan to avoid permission prompts
-    // Users can manually refresh if needed
-    // let _ = Command::new("nmcli")
-    //     .args(["device", "wifi", "rescan"])
-    //     .output();
-    
-    // Get available networks from cache
-    let output = Command::new("nmcli")
-        .args(["-t", "-f", "SSID,SIGNAL,SECURITY,IN-USE", "device", "wifi", "list"])
-        .output()
-        .map_err(|e| format!("Failed to get WiFi networks: {}", e))?;
-    
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    
-    // Get saved connections
-    let saved_output = Command::new("nmcli")
-        .args(["-t", "-f", "NAME", "connection", "show"])
-        .output()
-        .map_err(|e| format!("Failed to get saved connections: {}", e))?;
-    
-    let saved_stdout = String::from_utf8_lossy(&saved_output.stdout);
-    let saved_networks: Vec<&str> = saved_stdout.lines().collect();
-    
-    let mut networks = Vec::new();
-    
-    for line in stdout.lines() {
-        let parts: Vec<&str> = line.split(':').collect();
-        if parts.len() >= 4 {
-            let ssid = parts[0].to_string();
-            if ssid.is_empty() {
-                continue; // Skip hidden networks
+fn apply_monitor_config(
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, MonitorApplyState>,
+    monitors: Vec<Monitor>,
+    timeout_secs: Option<u64>,
+) -> Result<u64, String> {
+    let timeout_secs = timeout_secs.unwrap_or(DEFAULT_MONITOR_REVERT_SECS);
+
+    // Keep the layout from before any unconfirmed apply, so applying twice in
+    // a row still reverts to the last layout the user actually accepted
+    let mut pending = state.pending.lock().map_err(|e| e.to_string())?;
+    let previous = match pending.take() {
+        Some(existing) => existing.previous,
+        None => read_hyprland_monitors(true)?,
+    };
+
+    if let Err(e) = apply_monitors_live(&monitors) {
+        let _ = apply_monitors_live(&previous);
+        return Err(e);
+    }
+
+    let id = state.next_id.fetch_add(1, Ordering::SeqCst);
+    *pending = Some(PendingMonitorApply { id, previous });
+    drop(pending);
+
+    tauri::async_runtime::spawn(async move {
+        for remaining in (1..=timeout_secs).rev() {
+            if !is_pending_apply(&app_handle, id) {
+                return;
             }
-            
-            let signal_strength = parts[1].parse().unwrap_or(0);
-            let security = parts[2].to_string();
-            let in_use = parts[3] == "*";
-            let saved = saved_networks.contains(&ssid.as_str());
-            
-            networks.push(WifiNetwork {
-                ssid,
-                signal_strength,
-                security,
-                connected: in_use,
-                saved,
-                in_use,
-            });
+            if let Err(e) = app_handle.emit("monitor-config-countdown", remaining) {
+                eprintln!("Failed to emit monitor-config-countdown event: {}", e);
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         }
-    }
-    
-    // Remove duplicates and sort: saved networks first, then by signal strength
-    networks.sort_by(|a, b| {
-        match (a.saved, b.saved) {
-            (true, false) => std::cmp::Ordering::Less,  // a is saved, b is not - a comes first
-            (false, true) => std::cmp::Ordering::Greater, // b is saved, a is not - b comes first
-            _ => b.signal_strength.cmp(&a.signal_strength), // both same saved status - sort by signal
+
+        let state = app_handle.state::<MonitorApplyState>();
+        let previous = match state.pending.lock() {
+            Ok(mut pending) if pending.as_ref().map(|p| p.id) == Some(id) => {
+                pending.take().map(|p| p.previous)
+            }
+            _ => None,
+        };
+
+        if let Some(previous) = previous {
+            println!("Monitor configuration not confirmed, reverting...");
+            if let Err(e) = apply_monitors_live(&previous) {
+                eprintln!("Failed to revert monitor configuration: {}", e);
+            }
+            if let Err(e) = app_handle.emit("monitor-config-reverted", &previous) {
+                eprintln!("Failed to emit monitor-config-reverted event: {}", e);
+            }
         }
     });
-    networks.dedup_by(|a, b| a.ssid == b.ssid);
-    
-    Ok(networks)
-}
 
-#[tauri::command]
-fn connect_wifi(ssid: String, password: Option<String>) -> Result<String, String> {
-    let mut args = vec!["device", "wifi", "connect", &ssid];
-    
-    if let Some(pwd) = &password {
-        args.push("password");
-        args.push(pwd);
-    }
-    
-    let output = Command::new("nmcli")
-        .args(&args)
-        .output()
-        .map_err(|e| format!("Failed to connect to WiFi: {}", e))?;
-    
-    if output.status.success() {
-        Ok(format!("Connected to {}", ssid))
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to connect: {}", stderr))
-    }
+    Ok(timeout_secs)
 }
 
-#[tauri::command]
-fn disconnect_wifi(ssid: String) -> Result<String, String> {
-    let output = Command::new("nmcli")
-        .args(["connection", "down", &ssid])
-        .output()
-        .map_err(|e| format!("Failed to disconnect from WiFi: {}", e))?;
-    
-    if output.status.success() {
-        Ok(format!("Disconnected from {}", ssid))
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to disconnect: {}", stderr))
-    }
+fn is_pending_apply(app_handle: &tauri::AppHandle, id: u64) -> bool {
+    let state = app_handle.state::<MonitorApplyState>();
+    let pending = state.pending.lock();
+    matches!(pending.as_deref(), Ok(Some(p)) if p.id == id)
 }
 
 #[tauri::command]
-fn forget_wifi(ssid: String) -> Result<String, String> {
-    let output = Command::new("nmcli")
-        .args(["connection", "delete", &ssid])
-        .output()
-        .map_err(|e| format!("Failed to forget WiFi network: {}", e))?;
-    
-    if output.status.success() {
-        Ok(format!("Forgot network {}", ssid))
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to forget network: {}", stderr))
+fn confirm_monitor_config(state: tauri::State<'_, MonitorApplyState>) -> Result<String, String> {
+    let mut pending = state.pending.lock().map_err(|e| e.to_string())?;
+    match pending.take() {
+        Some(_) => Ok("Monitor configuration kept".to_string()),
+        None => Err("No monitor configuration is waiting for confirmation".to_string()),
     }
 }
 
 #[tauri::command]
-fn toggle_wifi(enable: bool) -> Result<String, String> {
-    let state = if enable { "on" } else { "off" };
-    
-    let output = Command::new("nmcli")
-        .args(["radio", "wifi", state])
-        .output()
-        .map_err(|e| format!("Failed to toggle WiFi: {}", e))?;
-    
-    if output.status.success() {
-        Ok(format!("WiFi turned {}", state))
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to toggle WiFi: {}", stderr))
-    }
+fn revert_monitor_config(state: tauri::State<'_, MonitorApplyState>) -> Result<Vec<Monitor>, String> {
+    let mut pending = state.pending.lock().map_err(|e| e.to_string())?;
+    let previous = pending
+        .take()
+        .ok_or("No monitor configuration is waiting for confirmation")?
+        .previous;
+
+    apply_monitors_live(&previous)?;
+    Ok(previous)
 }
 
 #[tauri::command]
@@ -895,6 +960,65 @@ async fn monitor_system_theme_changes(app_handle: tauri::AppHandle) -> Result<()
     Ok(())
 }
 
+async fn monitor_hyprland_events(app_handle: tauri::AppHandle) -> Result<(), String> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::UnixStream;
+
+    // Fails right away when we are not running inside Hyprland
+    let socket_path = hyprland::event_socket_path()?;
+
+    tokio::spawn(async move {
+        println!("Starting Hyprland event monitor on {}...", socket_path.display());
+
+        loop {
+            match UnixStream::connect(&socket_path).await {
+                Ok(stream) => {
+                    let mut lines = BufReader::new(stream).lines();
+
+                    // Outputs the last automatic profile switch was made for, so
+                    // the events caused by applying a profile don't re-trigger it
+                    let last_outputs: Arc<Mutex<Option<Vec<String>>>> = Arc::new(Mutex::new(None));
+
+                    loop {
+                        match lines.next_line().await {
+                            Ok(Some(line)) => {
+                                if let Some(event) = HyprlandEvent::parse(&line) {
+                                    if let Err(e) = app_handle.emit(event.event_name(), &event) {
+                                        eprintln!("Failed to emit {} event: {}", event.event_name(), e);
+                                    }
+
+                                    if matches!(event, HyprlandEvent::MonitorAdded { .. } | HyprlandEvent::MonitorRemoved { .. }) {
+                                        let handle = app_handle.clone();
+                                        let last_outputs = last_outputs.clone();
+                                        tauri::async_runtime::spawn_blocking(move || {
+                                            profiles::handle_monitor_change(&handle, &last_outputs)
+                                        });
+                                    }
+                                }
+                            }
+                            Ok(None) => break,
+                            Err(e) => {
+                                eprintln!("Error reading Hyprland events: {}", e);
+                                break;
+                            }
+                        }
+                    }
+
+                    println!("Hyprland event socket closed, reconnecting...");
+                }
+                Err(e) => {
+                    eprintln!("Failed to connect to Hyprland event socket: {}", e);
+                }
+            }
+
+            // Hyprland may be restarting; try again shortly
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        }
+    });
+
+    Ok(())
+}
+
 async fn get_gsetting(schema: &str, key: &str) -> Result<String, String> {
     let output = tokio::process::Command::new("gsettings")
         .args(["get", schema, key])
@@ -1627,20 +1751,3 @@ async fn system_update(
     ).await
 }
 
-#[tauri::command]
-async fn refresh_wifi_networks() -> Result<String, String> {
-    // Use pkexec for WiFi rescan to handle authentication properly
-    let privilege_cmd = get_privilege_command().await;
-    
-    let output = Command::new(&privilege_cmd)
-        .args(["nmcli", "device", "wifi", "rescan"])
-        .output()
-        .map_err(|e| format!("Failed to rescan WiFi networks: {}", e))?;
-    
-    if output.status.success() {
-        Ok("WiFi networks refreshed".to_string())
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(format!("Failed to refresh WiFi networks: {}", stderr))
-    }
-}
@@ -0,0 +1,315 @@
+// Typed display modes and custom modelines. Refresh rates are kept in mHz so
+// the mode written to monitors.conf is exactly the one the output advertised.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DisplayMode {
+    pub width: i32,
+    pub height: i32,
+    #[serde(rename = "refreshMhz")]
+    pub refresh_mhz: u32,
+}
+
+impl DisplayMode {
+    // Parses `1920x1080@59.94Hz`, `1920x1080@60` or `1920x1080` (assumed 60Hz)
+    pub fn parse(mode: &str) -> Option<Self> {
+        let mode = mode.trim();
+        let (resolution, refresh) = match mode.split_once('@') {
+            Some((resolution, refresh)) => (resolution, Some(refresh)),
+            None => (mode, None),
+        };
+
+        let (width, height) = resolution.split_once('x')?;
+        let width: i32 = width.trim().parse().ok()?;
+        let height: i32 = height.trim().parse().ok()?;
+
+        let refresh_hz: f64 = match refresh {
+            Some(refresh) => refresh.trim().trim_end_matches("Hz").parse().ok()?,
+            None => 60.0,
+        };
+
+        if width <= 0 || height <= 0 || refresh_hz <= 0.0 {
+            return None;
+        }
+
+        Some(Self {
+            width,
+            height,
+            refresh_mhz: hz_to_mhz(refresh_hz),
+        })
+    }
+
+    pub fn refresh_hz(&self) -> f64 {
+        self.refresh_mhz as f64 / 1000.0
+    }
+}
+
+// Formats as `WxH@R` with the refresh rate to the millihertz, which is how
+// monitor rules expect it
+impl fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}x{}@{}.{:03}",
+            self.width,
+            self.height,
+            self.refresh_mhz / 1000,
+            self.refresh_mhz % 1000
+        )
+    }
+}
+
+pub fn hz_to_mhz(hz: f64) -> u32 {
+    (hz * 1000.0).round() as u32
+}
+
+pub fn parse_modes(modes: &[String]) -> Vec<DisplayMode> {
+    modes.iter().filter_map(|m| DisplayMode::parse(m)).collect()
+}
+
+// The advertised mode with this resolution whose refresh rate is closest to
+// `refresh_hz`, as long as it is within half a hertz
+pub fn find_mode(modes: &[DisplayMode], width: i32, height: i32, refresh_hz: f64) -> Option<DisplayMode> {
+    let target = hz_to_mhz(refresh_hz) as i64;
+
+    modes
+        .iter()
+        .filter(|m| m.width == width && m.height == height)
+        .min_by_key(|m| (m.refresh_mhz as i64 - target).abs())
+        .filter(|m| (m.refresh_mhz as i64 - target).abs() <= 500)
+        .copied()
+}
+
+// An X11-style modeline:
+// `<clock MHz> <hdisplay> <hsync_start> <hsync_end> <htotal> <vdisplay> <vsync_start> <vsync_end> <vtotal> [flags]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Modeline {
+    pub clock_mhz: f64,
+    pub hdisplay: u32,
+    pub hsync_start: u32,
+    pub hsync_end: u32,
+    pub htotal: u32,
+    pub vdisplay: u32,
+    pub vsync_start: u32,
+    pub vsync_end: u32,
+    pub vtotal: u32,
+    pub flags: Vec<String>,
+}
+
+const MODELINE_FLAGS: [&str; 6] = ["+hsync", "-hsync", "+vsync", "-vsync", "interlace", "doublescan"];
+
+impl Modeline {
+    // Parses and validates a modeline, with or without the leading `modeline` keyword
+    pub fn parse(modeline: &str) -> Result<Self, String> {
+        let modeline = modeline.trim();
+        let modeline = modeline.strip_prefix("modeline").unwrap_or(modeline);
+        let parts: Vec<&str> = modeline.split_whitespace().collect();
+
+        if parts.len() < 9 {
+            return Err("A modeline needs a clock and eight timing values".to_string());
+        }
+
+        let clock_mhz: f64 = parts[0]
+            .parse()
+            .map_err(|_| format!("Invalid pixel clock: {}", parts[0]))?;
+
+        let mut timings = [0u32; 8];
+        for (i, value) in parts[1..9].iter().enumerate() {
+            timings[i] = value
+                .parse()
+                .map_err(|_| format!("Invalid timing value: {}", value))?;
+        }
+
+        let flags: Vec<String> = parts[9..].iter().map(|f| f.to_lowercase()).collect();
+        if let Some(flag) = flags.iter().find(|f| !MODELINE_FLAGS.contains(&f.as_str())) {
+            return Err(format!("Unknown modeline flag: {}", flag));
+        }
+
+        let [hdisplay, hsync_start, hsync_end, htotal, vdisplay, vsync_start, vsync_end, vtotal] = timings;
+        let modeline = Self {
+            clock_mhz,
+            hdisplay,
+            hsync_start,
+            hsync_end,
+            htotal,
+            vdisplay,
+            vsync_start,
+            vsync_end,
+            vtotal,
+            flags,
+        };
+        modeline.validate()?;
+        Ok(modeline)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.clock_mhz <= 0.0 {
+            return Err("Pixel clock must be greater than zero".to_string());
+        }
+        if self.hdisplay == 0 || self.vdisplay == 0 {
+            return Err("Resolution must be greater than zero".to_string());
+        }
+        if !(self.hdisplay <= self.hsync_start && self.hsync_start <= self.hsync_end && self.hsync_end <= self.htotal) {
+            return Err("Horizontal timings must satisfy hdisplay <= hsync_start <= hsync_end <= htotal".to_string());
+        }
+        if !(self.vdisplay <= self.vsync_start && self.vsync_start <= self.vsync_end && self.vsync_end <= self.vtotal) {
+            return Err("Vertical timings must satisfy vdisplay <= vsync_start <= vsync_end <= vtotal".to_string());
+        }
+        if self.htotal == self.hdisplay || self.vtotal == self.vdisplay {
+            return Err("Modeline has no blanking interval".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn refresh_mhz(&self) -> u32 {
+        let mut refresh = self.clock_mhz * 1_000_000.0 / (self.htotal as f64 * self.vtotal as f64);
+        if self.flags.iter().any(|f| f == "interlace") {
+            refresh *= 2.0;
+        }
+        if self.flags.iter().any(|f| f == "doublescan") {
+            refresh /= 2.0;
+        }
+        hz_to_mhz(refresh)
+    }
+
+    pub fn mode(&self) -> DisplayMode {
+        DisplayMode {
+            width: self.hdisplay as i32,
+            height: self.vdisplay as i32,
+            refresh_mhz: self.refresh_mhz(),
+        }
+    }
+}
+
+// Formats without the `modeline` keyword, the way it follows it in a monitor rule
+impl fmt::Display for Modeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {} {} {} {}",
+            self.clock_mhz,
+            self.hdisplay,
+            self.hsync_start,
+            self.hsync_end,
+            self.htotal,
+            self.vdisplay,
+            self.vsync_start,
+            self.vsync_end,
+            self.vtotal
+        )?;
+        for flag in &self.flags {
+            write!(f, " {}", flag)?;
+        }
+        Ok(())
+    }
+}
+
+#[tauri::command]
+pub fn parse_display_mode(mode: String) -> Result<DisplayMode, String> {
+    DisplayMode::parse(&mode).ok_or_else(|| format!("Invalid display mode: {}", mode))
+}
+
+// Validates a custom modeline and returns the mode it produces
+#[tauri::command]
+pub fn validate_modeline(modeline: String) -> Result<DisplayMode, String> {
+    Ok(Modeline::parse(&modeline)?.mode())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_prints_modes() {
+        let cases = [
+            ("1920x1080@59.94Hz", 1920, 1080, 59_940, "1920x1080@59.940"),
+            ("2560x1440@143.912Hz", 2560, 1440, 143_912, "2560x1440@143.912"),
+            ("1920x1080@60", 1920, 1080, 60_000, "1920x1080@60.000"),
+            ("3840x2160", 3840, 2160, 60_000, "3840x2160@60.000"),
+            (" 1280x720@50.00Hz ", 1280, 720, 50_000, "1280x720@50.000"),
+        ];
+        for (input, width, height, refresh_mhz, printed) in cases {
+            let mode = DisplayMode::parse(input).unwrap_or_else(|| panic!("{} should parse", input));
+            assert_eq!(mode, DisplayMode { width, height, refresh_mhz }, "{}", input);
+            assert_eq!(mode.to_string(), printed, "{}", input);
+            // What gets written to monitors.conf reads back as the same mode
+            assert_eq!(DisplayMode::parse(printed), Some(mode), "{}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_modes() {
+        for input in ["", "1920", "1920x", "x1080", "0x1080@60", "1920x1080@0", "1920x1080@fast", "-1920x1080@60"] {
+            assert_eq!(DisplayMode::parse(input), None, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn finds_the_closest_advertised_mode() {
+        let modes = parse_modes(&[
+            "1920x1080@60.00Hz".to_string(),
+            "1920x1080@59.94Hz".to_string(),
+            "1920x1080@50.00Hz".to_string(),
+            "2560x1440@59.95Hz".to_string(),
+        ]);
+        let cases = [
+            (1920, 1080, 60.0, Some(60_000)),
+            (1920, 1080, 59.94, Some(59_940)),
+            (1920, 1080, 59.9, Some(59_940)),
+            (1920, 1080, 60.4, Some(60_000)),
+            (1920, 1080, 50.5, Some(50_000)),
+            (1920, 1080, 50.6, None),
+            (1920, 1080, 75.0, None),
+            (2560, 1440, 60.0, Some(59_950)),
+            (1280, 720, 60.0, None),
+        ];
+        for (width, height, refresh_hz, expected) in cases {
+            assert_eq!(
+                find_mode(&modes, width, height, refresh_hz).map(|m| m.refresh_mhz),
+                expected,
+                "{}x{}@{}",
+                width,
+                height,
+                refresh_hz
+            );
+        }
+    }
+
+    #[test]
+    fn parses_modelines() {
+        let modeline = Modeline::parse("modeline 148.50 1920 2008 2052 2200 1080 1084 1089 1125 +hsync +vsync").unwrap();
+        assert_eq!(modeline.mode(), DisplayMode { width: 1920, height: 1080, refresh_mhz: 60_000 });
+        assert_eq!(modeline.to_string(), "148.5 1920 2008 2052 2200 1080 1084 1089 1125 +hsync +vsync");
+
+        let interlaced = Modeline::parse("74.25 1920 2008 2052 2200 1080 1084 1094 1125 Interlace").unwrap();
+        assert_eq!(interlaced.refresh_mhz(), 60_000);
+    }
+
+    #[test]
+    fn rejects_malformed_modelines() {
+        let cases = [
+            // Too few fields
+            "148.50 1920 2008 2052 2200 1080 1084 1089",
+            "",
+            // A tenth value that is not a flag
+            "148.50 1920 2008 2052 2200 1080 1084 1089 1125 1200",
+            // Not a number
+            "fast 1920 2008 2052 2200 1080 1084 1089 1125",
+            "148.50 1920 2008 2052 2200 1080 1084 1089 -1125",
+            // Zero or negative clock
+            "0 1920 2008 2052 2200 1080 1084 1089 1125",
+            "-148.50 1920 2008 2052 2200 1080 1084 1089 1125",
+            // Sync values out of order
+            "148.50 1920 2052 2008 2200 1080 1084 1089 1125",
+            "148.50 1920 2008 2052 2200 1080 1089 1084 1125",
+            "148.50 1920 2008 2052 2000 1080 1084 1089 1125",
+            // Zero size or no blanking
+            "148.50 0 2008 2052 2200 1080 1084 1089 1125",
+            "148.50 1920 1920 1920 1920 1080 1084 1089 1125",
+        ];
+        for modeline in cases {
+            assert!(Modeline::parse(modeline).is_err(), "{:?}", modeline);
+        }
+    }
+}
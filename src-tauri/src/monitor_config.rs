@@ -0,0 +1,448 @@
+// Reading and writing the generated monitors.conf. The file is parsed back
+// into `Monitor` values so that saving only replaces the outputs that were
+// passed in, keeping the rules for outputs that are unplugged or disabled.
+// Rules that can't be represented exactly are kept as written.
+use crate::modes::{self, DisplayMode, Modeline};
+use crate::autogen;
+use crate::hypr_sources::{escape_comment, strip_comment};
+use crate::Monitor;
+use std::fs;
+use std::path::PathBuf;
+
+const MONITORS_CONFIG: &str = "monitors.conf";
+
+pub fn monitors_config_path() -> PathBuf {
+    autogen::autogen_dir().join(MONITORS_CONFIG)
+}
+
+// The mode argument of a monitor rule: the custom modeline if there is one,
+// otherwise the advertised mode matching width/height/refresh, written exactly
+pub fn format_mode(monitor: &Monitor) -> String {
+    if let Some(modeline) = monitor.modeline.as_deref().and_then(|m| Modeline::parse(m).ok()) {
+        return format!("modeline {}", modeline);
+    }
+
+    modes::find_mode(&monitor.modes, monitor.width, monitor.height, monitor.refresh_rate)
+        .unwrap_or(DisplayMode {
+            width: monitor.width,
+            height: monitor.height,
+            refresh_mhz: modes::hz_to_mhz(monitor.refresh_rate),
+        })
+        .to_string()
+}
+
+// Formats a monitor rule without the `monitor =` prefix: `name, mode, XxY, scale`
+// followed by any optional arguments that differ from Hyprland's defaults
+pub fn format_monitor_rule(monitor: &Monitor) -> String {
+    let mut rule = format!(
+        "{}, {}, {}x{}, {}",
+        monitor.rule_target(),
+        format_mode(monitor),
+        monitor.x,
+        monitor.y,
+        format_scale(monitor.scale)
+    );
+
+    if monitor.transform != 0 {
+        rule.push_str(&format!(", transform, {}", monitor.transform));
+    }
+    if let Some(mirror) = &monitor.mirror {
+        rule.push_str(&format!(", mirror, {}", mirror));
+    }
+    if let Some(bitdepth) = monitor.bitdepth {
+        rule.push_str(&format!(", bitdepth, {}", bitdepth));
+    }
+    if let Some(vrr) = monitor.vrr {
+        rule.push_str(&format!(", vrr, {}", vrr));
+    }
+    if let Some(cm) = &monitor.cm {
+        rule.push_str(&format!(", cm, {}", cm));
+    }
+    if let Some(icc) = &monitor.icc_profile {
+        rule.push_str(&format!(", icc, {}", icc));
+    }
+
+    rule
+}
+
+// Two decimals for the common scales, more for fractions like 4/3 that only
+// produce whole logical pixels when written precisely
+fn format_scale(scale: f64) -> String {
+    let rounded = format!("{:.2}", scale);
+    if (rounded.parse::<f64>().unwrap_or(scale) - scale).abs() < 1e-9 {
+        rounded
+    } else {
+        format!("{:.6}", scale)
+    }
+}
+
+// One output's lines in monitors.conf. Rules written out in full become a
+// `Monitor`; anything the parser would have to fill in (`preferred`, `auto`,
+// options it doesn't know, a lone `disable`, the catch-all `monitor = , ...`)
+// is kept as written and saved back verbatim.
+#[derive(Debug, Clone)]
+pub enum SavedRule {
+    Monitor(Box<Monitor>),
+    Raw { target: String, lines: Vec<String> },
+}
+
+impl SavedRule {
+    // Whether saving `monitor` replaces this rule
+    fn is_replaced_by(&self, monitor: &Monitor) -> bool {
+        match self {
+            SavedRule::Monitor(saved) => saved.same_output(monitor),
+            SavedRule::Raw { target, .. } => {
+                let by_description = !monitor.description.is_empty()
+                    && target.strip_prefix("desc:") == Some(monitor.description.as_str());
+                !target.is_empty() && (*target == monitor.name || *target == monitor.rule_target() || by_description)
+            }
+        }
+    }
+}
+
+fn format_saved_rules(rules: &[SavedRule]) -> String {
+    let mut config_content = String::from(autogen::AUTOGEN_HEADER);
+
+    for rule in rules {
+        match rule {
+            SavedRule::Monitor(monitor) => {
+                config_content.push_str(&format!("monitor = {}\n", escape_comment(&format_monitor_rule(monitor))));
+
+                if monitor.disabled {
+                    config_content
+                        .push_str(&format!("monitor = {}, disabled\n", escape_comment(&monitor.rule_target())));
+                }
+            }
+            SavedRule::Raw { lines, .. } => {
+                for line in lines {
+                    config_content.push_str(line);
+                    config_content.push('\n');
+                }
+            }
+        }
+    }
+
+    config_content
+}
+
+// Writes the monitors to the autogen monitors.conf, keeping the saved rules of
+// any output that is not part of `monitors`, and returns the file's path
+pub fn write_monitor_config(monitors: &[Monitor]) -> Result<PathBuf, String> {
+    let merged = merge_saved_rules(read_saved_rules()?, monitors);
+    let config_content = format_saved_rules(&merged);
+
+    println!("Generated monitor configuration:\n{}", config_content);
+
+    autogen::write_autogen_file(MONITORS_CONFIG, &config_content)
+}
+
+// Drops the saved rules of the output called `name`
+pub fn remove_monitor_config(name: &str) -> Result<(), String> {
+    let saved = read_saved_rules()?;
+    let remaining: Vec<SavedRule> = saved
+        .iter()
+        .filter(|rule| match rule {
+            SavedRule::Monitor(monitor) => monitor.name != name,
+            SavedRule::Raw { target, .. } => target != name,
+        })
+        .cloned()
+        .collect();
+    if remaining.len() == saved.len() {
+        return Ok(());
+    }
+
+    autogen::write_autogen_file(MONITORS_CONFIG, &format_saved_rules(&remaining))?;
+    Ok(())
+}
+
+// Saved rules that `monitors` don't replace come first, in their saved order,
+// followed by `monitors` themselves
+pub fn merge_saved_rules(saved: Vec<SavedRule>, monitors: &[Monitor]) -> Vec<SavedRule> {
+    let mut merged: Vec<SavedRule> = saved
+        .into_iter()
+        .filter(|rule| !monitors.iter().any(|m| rule.is_replaced_by(m)))
+        .collect();
+    merged.extend(monitors.iter().map(|m| SavedRule::Monitor(Box::new(m.clone()))));
+    merged
+}
+
+// The same merge for the outputs the saved rules fully describe, e.g. to
+// validate the layout that saving `monitors` produces
+pub fn merge_monitors(saved: Vec<Monitor>, monitors: &[Monitor]) -> Vec<Monitor> {
+    let mut merged: Vec<Monitor> = saved
+        .into_iter()
+        .filter(|s| !monitors.iter().any(|m| m.same_output(s)))
+        .collect();
+    merged.extend(monitors.iter().cloned());
+    merged
+}
+
+// Reads the saved monitors.conf, returning no rules when it doesn't exist yet
+pub fn read_saved_rules() -> Result<Vec<SavedRule>, String> {
+    let config_file = monitors_config_path();
+    if !config_file.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&config_file)
+        .map_err(|e| format!("Failed to read {}: {}", config_file.display(), e))?;
+    Ok(parse_saved_rules(&content))
+}
+
+// The saved outputs whose rules are written out in full
+pub fn read_monitor_config() -> Result<Vec<Monitor>, String> {
+    Ok(saved_monitors(read_saved_rules()?))
+}
+
+fn saved_monitors(rules: Vec<SavedRule>) -> Vec<Monitor> {
+    rules
+        .into_iter()
+        .filter_map(|rule| match rule {
+            SavedRule::Monitor(monitor) => Some(*monitor),
+            SavedRule::Raw { .. } => None,
+        })
+        .collect()
+}
+
+// Arguments of a `monitor = ...` line with comments stripped, target first;
+// None for any other line
+fn monitor_rule_args(line: &str) -> Option<Vec<String>> {
+    let line = strip_comment(line);
+    let (key, value) = line.split_once('=')?;
+    if key.trim() != "monitor" {
+        return None;
+    }
+    Some(value.split(',').map(|a| a.trim().to_string()).collect())
+}
+
+fn is_disable_rule(args: &[String]) -> bool {
+    args.len() == 2 && matches!(args[1].as_str(), "disable" | "disabled")
+}
+
+pub fn parse_saved_rules(content: &str) -> Vec<SavedRule> {
+    // Lines grouped by the output they configure, in the order outputs first appear
+    let mut outputs: Vec<(String, Vec<String>)> = Vec::new();
+    for line in content.lines() {
+        let Some(args) = monitor_rule_args(line) else {
+            continue;
+        };
+        let line = line.trim().to_string();
+        match outputs.iter_mut().find(|(target, _)| *target == args[0]) {
+            Some((_, lines)) => lines.push(line),
+            None => outputs.push((args[0].clone(), vec![line])),
+        }
+    }
+
+    outputs
+        .into_iter()
+        .enumerate()
+        .map(|(index, (target, lines))| match parse_output_rules(index as i32, &target, &lines) {
+            Some(monitor) => SavedRule::Monitor(Box::new(monitor)),
+            None => SavedRule::Raw { target, lines },
+        })
+        .collect()
+}
+
+// The monitor described by one output's lines, when they have the form this
+// module writes (a rule, optionally followed by `disabled`) and every value
+// is spelled out
+fn parse_output_rules(id: i32, target: &str, lines: &[String]) -> Option<Monitor> {
+    if target.is_empty() {
+        return None;
+    }
+    let args: Vec<Vec<String>> = lines.iter().filter_map(|line| monitor_rule_args(line)).collect();
+    // `monitor = NAME, disable` only toggles the output off; the rule before
+    // it holds the mode it comes back with
+    let (rule, disabled) = match args.as_slice() {
+        [rule] => (rule, false),
+        [rule, disable] if is_disable_rule(disable) => (rule, true),
+        _ => return None,
+    };
+    if is_disable_rule(rule) {
+        return None;
+    }
+
+    let mut monitor = default_monitor(id, target);
+    // The connector of a `desc:` rule isn't known until the display is
+    // plugged in, so the description doubles as its name
+    if let Some(description) = target.strip_prefix("desc:") {
+        monitor.name = description.to_string();
+        monitor.description = description.to_string();
+        monitor.match_by_description = true;
+        monitor.id = Monitor::stable_id(description, None, monitor.id);
+    }
+    apply_rule_args(&mut monitor, &rule[1..])?;
+    monitor.disabled = disabled;
+    Some(monitor)
+}
+
+fn default_monitor(id: i32, name: &str) -> Monitor {
+    Monitor {
+        id,
+        name: name.to_string(),
+        width: 1920,
+        height: 1080,
+        x: 0,
+        y: 0,
+        refresh_rate: 60.0,
+        disabled: false,
+        scale: 1.0,
+        available_modes: Vec::new(),
+        transform: 0,
+        mirror: None,
+        bitdepth: None,
+        vrr: None,
+        cm: None,
+        description: String::new(),
+        make: String::new(),
+        model: String::new(),
+        serial: String::new(),
+        match_by_description: false,
+        modes: Vec::new(),
+        modeline: None,
+        is_virtual: false,
+        gpu: None,
+        icc_profile: None,
+    }
+}
+
+fn parse_pair<T: std::str::FromStr>(value: &str) -> Option<(T, T)> {
+    let (a, b) = value.split_once('x')?;
+    Some((a.parse().ok()?, b.parse().ok()?))
+}
+
+// Applies `RES@HZ, XxY, SCALE[, key, value]...` to a monitor. Returns None for
+// anything Hyprland resolves itself (`preferred`, `auto`, ...) and for options
+// `Monitor` has no field for, which would be lost on saving.
+fn apply_rule_args(monitor: &mut Monitor, args: &[String]) -> Option<()> {
+    if args.len() < 3 || !(args.len() - 3).is_multiple_of(2) {
+        return None;
+    }
+
+    if let Some(modeline) = args[0].strip_prefix("modeline") {
+        let mode = Modeline::parse(modeline).ok()?.mode();
+        monitor.width = mode.width;
+        monitor.height = mode.height;
+        monitor.refresh_rate = mode.refresh_hz();
+        monitor.modeline = Some(modeline.trim().to_string());
+    } else {
+        let (resolution, refresh) = args[0].split_once('@')?;
+        (monitor.width, monitor.height) = parse_pair(resolution)?;
+        monitor.refresh_rate = refresh.trim_end_matches("Hz").parse().ok()?;
+    }
+
+    (monitor.x, monitor.y) = parse_pair(&args[1])?;
+    monitor.scale = args[2].parse().ok().filter(|scale: &f64| *scale > 0.0)?;
+
+    for option in args[3..].chunks(2) {
+        let value = option[1].as_str();
+        match option[0].as_str() {
+            "transform" => monitor.transform = value.parse().ok()?,
+            "mirror" => monitor.mirror = Some(value.to_string()),
+            "bitdepth" => monitor.bitdepth = Some(value.parse().ok()?),
+            "vrr" => monitor.vrr = Some(value.parse().ok()?),
+            "cm" => monitor.cm = Some(value.to_string()),
+            "icc" => monitor.icc_profile = Some(value.to_string()),
+            _ => return None,
+        }
+    }
+    Some(())
+}
+
+// Monitors as saved in monitors.conf, including outputs that are currently
+// disabled or not connected
+#[tauri::command]
+pub fn get_saved_monitors() -> Result<Vec<Monitor>, String> {
+    read_monitor_config()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved_monitor(name: &str, x: i32) -> Monitor {
+        Monitor {
+            width: 2560,
+            height: 1440,
+            refresh_rate: 144.0,
+            x,
+            ..default_monitor(0, name)
+        }
+    }
+
+    fn parse_monitor_config(content: &str) -> Vec<Monitor> {
+        saved_monitors(parse_saved_rules(content))
+    }
+
+    // The lines after the generated header
+    fn body(config: &str) -> Vec<&str> {
+        config.strip_prefix(autogen::AUTOGEN_HEADER).unwrap().lines().collect()
+    }
+
+    #[test]
+    fn keeps_escaped_hash_in_rules() {
+        let monitors = parse_monitor_config("monitor = desc:ACME Display ##2, 1920x1080@60, 0x0, 1 # main screen\n");
+        assert_eq!(monitors.len(), 1);
+        assert_eq!(monitors[0].rule_target(), "desc:ACME Display #2");
+        assert_eq!((monitors[0].width, monitors[0].height), (1920, 1080));
+
+        let rules = parse_saved_rules("monitor = desc:ACME Display ##2, 1920x1080@60, 0x0, 1 # main screen\n");
+        let written = format_saved_rules(&merge_saved_rules(rules, &[]));
+        assert!(written.contains("monitor = desc:ACME Display ##2, "), "{}", written);
+        assert_eq!(parse_monitor_config(&written)[0].rule_target(), "desc:ACME Display #2");
+    }
+
+    #[test]
+    fn full_rules_round_trip() {
+        let config = "\
+monitor = DP-1, 2560x1440@143.912, 1920x0, 1.25, transform, 1, bitdepth, 10, vrr, 2, cm, hdr
+monitor = eDP-1, 1920x1200@60.000, 0x0, 1.00
+monitor = eDP-1, disabled
+";
+        let monitors = parse_monitor_config(config);
+        assert_eq!(monitors.len(), 2);
+        assert_eq!((monitors[0].x, monitors[0].scale, monitors[0].vrr), (1920, 1.25, Some(2)));
+        assert!(monitors[1].disabled && monitors[1].height == 1200);
+
+        let written = format_saved_rules(&merge_saved_rules(parse_saved_rules(config), &[]));
+        assert_eq!(
+            body(&written),
+            [
+                "monitor = DP-1, 2560x1440@143.912, 1920x0, 1.25, transform, 1, bitdepth, 10, vrr, 2, cm, hdr",
+                "monitor = eDP-1, 1920x1200@60.000, 0x0, 1.00",
+                "monitor = eDP-1, disabled",
+            ]
+        );
+    }
+
+    #[test]
+    fn rules_with_values_left_to_hyprland_are_kept_verbatim() {
+        let config = "\
+monitor = HDMI-A-1, preferred, auto, 1
+monitor = DP-2, highrr, 0x0, auto # docking station
+monitor = DP-3, 1920x1080@60, auto-right, 1
+monitor = DP-4, 1920x1080@60, 0x0, 1, sdrbrightness, 1.2
+monitor = eDP-1, disable
+monitor = , preferred, auto, 1
+";
+        let rules = parse_saved_rules(config);
+        assert!(rules.iter().all(|rule| matches!(rule, SavedRule::Raw { .. })));
+        assert!(parse_monitor_config(config).is_empty());
+
+        // Saving another output leaves every one of them untouched
+        let written = format_saved_rules(&merge_saved_rules(rules, &[saved_monitor("DP-1", 0)]));
+        let mut expected: Vec<&str> = config.lines().collect();
+        expected.push("monitor = DP-1, 2560x1440@144.000, 0x0, 1.00");
+        assert_eq!(body(&written), expected);
+    }
+
+    #[test]
+    fn saving_an_output_replaces_its_verbatim_rule() {
+        let config = "monitor = DP-1, preferred, auto, 1\nmonitor = , preferred, auto, 1\n";
+        let written = format_saved_rules(&merge_saved_rules(parse_saved_rules(config), &[saved_monitor("DP-1", 0)]));
+        assert_eq!(
+            body(&written),
+            ["monitor = , preferred, auto, 1", "monitor = DP-1, 2560x1440@144.000, 0x0, 1.00"]
+        );
+    }
+}
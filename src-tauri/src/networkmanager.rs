@@ -0,0 +1,591 @@
+// Client for NetworkManager's D-Bus API (org.freedesktop.NetworkManager).
+// Every helper takes the bus connection so it can be pointed at a mock
+// service on a private bus instead of the system bus.
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+use zbus::proxy::{Builder, CacheProperties, Defaults};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+use zbus::{proxy, Connection, DBusError};
+
+// NMDeviceType
+pub const DEVICE_TYPE_WIFI: u32 = 2;
+// NMActiveConnectionState
+const ACTIVE_STATE_ACTIVATED: u32 = 2;
+const ACTIVE_STATE_DEACTIVATED: u32 = 4;
+// NMDeviceStateReason values that mean the key was wrong or missing
+const REASON_NO_SECRETS: u32 = 7;
+const REASON_SUPPLICANT_DISCONNECT: u32 = 8;
+// NM80211ApFlags / NM80211ApSecurityFlags
+pub const AP_FLAGS_PRIVACY: u32 = 0x1;
+pub const AP_SEC_KEY_MGMT_PSK: u32 = 0x100;
+pub const AP_SEC_KEY_MGMT_802_1X: u32 = 0x200;
+pub const AP_SEC_KEY_MGMT_SAE: u32 = 0x400;
+pub const AP_SEC_KEY_MGMT_OWE: u32 = 0x800;
+
+const ACTIVATION_TIMEOUT: Duration = Duration::from_secs(45);
+const ACTIVATION_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// Connection settings as returned by GetSettings (a{sa{sv}})
+pub type ConnectionSettings = HashMap<String, HashMap<String, OwnedValue>>;
+// Connection settings as passed to AddConnection and friends
+pub type NewConnectionSettings<'a> = HashMap<&'a str, HashMap<&'a str, Value<'a>>>;
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+pub trait NetworkManager {
+    fn get_devices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+
+    fn activate_connection(
+        &self,
+        connection: &ObjectPath<'_>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    fn add_and_activate_connection(
+        &self,
+        connection: NewConnectionSettings<'_>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> zbus::Result<(OwnedObjectPath, OwnedObjectPath)>;
+
+    fn deactivate_connection(&self, active_connection: &ObjectPath<'_>) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn wireless_enabled(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn set_wireless_enabled(&self, value: bool) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn active_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Device",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Device {
+    #[zbus(property)]
+    fn interface(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn device_type(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn active_connection(&self) -> zbus::Result<OwnedObjectPath>;
+
+    // (NMDeviceState, NMDeviceStateReason)
+    #[zbus(property)]
+    fn state_reason(&self) -> zbus::Result<(u32, u32)>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Device.Wireless",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Wireless {
+    fn get_all_access_points(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+
+    fn request_scan(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn active_access_point(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.AccessPoint",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait AccessPoint {
+    #[zbus(property)]
+    fn ssid(&self) -> zbus::Result<Vec<u8>>;
+
+    #[zbus(property)]
+    fn strength(&self) -> zbus::Result<u8>;
+
+    #[zbus(property)]
+    fn flags(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn wpa_flags(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn rsn_flags(&self) -> zbus::Result<u32>;
+
+    // BSSID
+    #[zbus(property)]
+    fn hw_address(&self) -> zbus::Result<String>;
+
+    // MHz
+    #[zbus(property)]
+    fn frequency(&self) -> zbus::Result<u32>;
+
+    // kbit/s
+    #[zbus(property)]
+    fn max_bitrate(&self) -> zbus::Result<u32>;
+
+    // CLOCK_BOOTTIME seconds of the last scan that found it, -1 if never
+    #[zbus(property)]
+    fn last_seen(&self) -> zbus::Result<i32>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Settings",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager/Settings"
+)]
+pub trait Settings {
+    fn list_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Settings.Connection",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait SettingsConnection {
+    fn get_settings(&self) -> zbus::Result<ConnectionSettings>;
+
+    fn get_secrets(&self, setting_name: &str) -> zbus::Result<ConnectionSettings>;
+
+    fn update(&self, properties: NewConnectionSettings<'_>) -> zbus::Result<()>;
+
+    fn delete(&self) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Connection.Active",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait ActiveConnection {
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[zbus(property, name = "Type")]
+    fn connection_type(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn connection(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn state(&self) -> zbus::Result<u32>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NetworkErrorKind {
+    // NetworkManager isn't running or the system bus can't be reached
+    Unavailable,
+    // polkit refused the action
+    PermissionDenied,
+    NoWifiDevice,
+    // No such network, connection or access point
+    NotFound,
+    // Wrong or missing password
+    AuthFailed,
+    ActivationFailed,
+    Timeout,
+    InvalidInput,
+    DBus,
+}
+
+// Error returned by the network commands, serialized as `{ kind, message }`
+// so the frontend can react to the kind and still show the message
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkError {
+    pub kind: NetworkErrorKind,
+    pub message: String,
+}
+
+impl NetworkError {
+    pub fn new(kind: NetworkErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(NetworkErrorKind::NotFound, message)
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(NetworkErrorKind::InvalidInput, message)
+    }
+
+    // Classifies a D-Bus error reply by its error name
+    fn from_dbus_error(name: &str, message: String) -> Self {
+        let kind = match name {
+            "org.freedesktop.DBus.Error.ServiceUnknown" | "org.freedesktop.DBus.Error.NameHasNoOwner" => {
+                NetworkErrorKind::Unavailable
+            }
+            "org.freedesktop.DBus.Error.AccessDenied"
+            | "org.freedesktop.DBus.Error.InteractiveAuthorizationRequired"
+            | "org.freedesktop.NetworkManager.PermissionDenied" => NetworkErrorKind::PermissionDenied,
+            "org.freedesktop.DBus.Error.UnknownObject" => NetworkErrorKind::NotFound,
+            _ if name.ends_with(".UnknownConnection") || name.ends_with(".UnknownDevice") => {
+                NetworkErrorKind::NotFound
+            }
+            _ if name.ends_with(".InvalidProperty") || name.ends_with(".InvalidArguments") => {
+                NetworkErrorKind::InvalidInput
+            }
+            _ => NetworkErrorKind::DBus,
+        };
+        let message = if message.is_empty() { name.to_string() } else { message };
+        Self::new(kind, message)
+    }
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+impl From<zbus::Error> for NetworkError {
+    fn from(e: zbus::Error) -> Self {
+        match e {
+            zbus::Error::MethodError(name, message, _) => {
+                Self::from_dbus_error(name.as_str(), message.unwrap_or_default())
+            }
+            zbus::Error::FDO(e) => Self::from_dbus_error(e.name().as_str(), e.description().unwrap_or_default().to_string()),
+            zbus::Error::InputOutput(_) | zbus::Error::Address(_) | zbus::Error::Handshake(_) => {
+                Self::new(NetworkErrorKind::Unavailable, format!("Cannot reach the system bus: {}", e))
+            }
+            e => Self::new(NetworkErrorKind::DBus, e.to_string()),
+        }
+    }
+}
+
+impl From<zbus::zvariant::Error> for NetworkError {
+    fn from(e: zbus::zvariant::Error) -> Self {
+        Self::new(NetworkErrorKind::DBus, format!("Unexpected value from NetworkManager: {}", e))
+    }
+}
+
+pub type NetworkResult<T> = Result<T, NetworkError>;
+
+pub async fn system_bus() -> NetworkResult<Connection> {
+    Connection::system().await.map_err(|e| {
+        NetworkError::new(NetworkErrorKind::Unavailable, format!("Cannot reach the system bus: {}", e))
+    })
+}
+
+// Proxy for the object at `path`, reading properties on demand rather than
+// subscribing to their changes
+pub async fn proxy_at<'a, P>(conn: &Connection, path: impl Into<ObjectPath<'a>>) -> NetworkResult<P>
+where
+    P: From<zbus::Proxy<'a>> + Defaults,
+{
+    Ok(Builder::<P>::new(conn)
+        .path(path.into())?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?)
+}
+
+pub async fn network_manager(conn: &Connection) -> NetworkResult<NetworkManagerProxy<'static>> {
+    Ok(Builder::<NetworkManagerProxy>::new(conn)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?)
+}
+
+// "/" stands for "none" wherever NetworkManager returns an object path
+pub fn is_none_path(path: &ObjectPath<'_>) -> bool {
+    path.as_str() == "/"
+}
+
+pub fn none_path() -> ObjectPath<'static> {
+    ObjectPath::from_static_str_unchecked("/")
+}
+
+// The first Wi-Fi device
+pub async fn wifi_device(conn: &Connection) -> NetworkResult<OwnedObjectPath> {
+    for path in network_manager(conn).await?.get_devices().await? {
+        let device: DeviceProxy = proxy_at(conn, &path).await?;
+        if device.device_type().await? == DEVICE_TYPE_WIFI {
+            return Ok(path);
+        }
+    }
+    Err(NetworkError::new(NetworkErrorKind::NoWifiDevice, "No Wi-Fi device found"))
+}
+
+#[derive(Debug, Clone)]
+pub struct AccessPointInfo {
+    pub path: OwnedObjectPath,
+    pub ssid: Vec<u8>,
+    pub strength: u8,
+    pub flags: u32,
+    pub wpa_flags: u32,
+    pub rsn_flags: u32,
+    pub bssid: String,
+    pub frequency: u32,
+    pub max_bitrate: u32,
+    pub last_seen: Option<i64>,
+}
+
+impl AccessPointInfo {
+    pub fn ssid_string(&self) -> String {
+        String::from_utf8_lossy(&self.ssid).to_string()
+    }
+
+    // Security in nmcli's words: "", "WEP", "WPA1", "WPA2", "WPA3", "OWE", "802.1X"
+    pub fn security(&self) -> String {
+        let key_mgmt = self.wpa_flags | self.rsn_flags;
+        let mut parts = Vec::new();
+
+        if self.flags & AP_FLAGS_PRIVACY != 0 && self.wpa_flags == 0 && self.rsn_flags == 0 {
+            parts.push("WEP");
+        }
+        if self.wpa_flags != 0 {
+            parts.push("WPA1");
+        }
+        if self.rsn_flags & (AP_SEC_KEY_MGMT_PSK | AP_SEC_KEY_MGMT_802_1X) != 0 {
+            parts.push("WPA2");
+        }
+        if self.rsn_flags & AP_SEC_KEY_MGMT_SAE != 0 {
+            parts.push("WPA3");
+        }
+        if self.rsn_flags & AP_SEC_KEY_MGMT_OWE != 0 {
+            parts.push("OWE");
+        }
+        if key_mgmt & AP_SEC_KEY_MGMT_802_1X != 0 {
+            parts.push("802.1X");
+        }
+
+        parts.join(" ")
+    }
+
+    pub fn is_secured(&self) -> bool {
+        self.flags & AP_FLAGS_PRIVACY != 0 || self.wpa_flags != 0 || self.rsn_flags != 0
+    }
+
+    // WPA-Enterprise, which needs 802.1X credentials rather than a password
+    pub fn is_enterprise(&self) -> bool {
+        (self.wpa_flags | self.rsn_flags) & AP_SEC_KEY_MGMT_802_1X != 0
+    }
+}
+
+// "2.4 GHz", "5 GHz" or "6 GHz" for a frequency in MHz
+pub fn frequency_band(frequency: u32) -> Option<&'static str> {
+    match frequency {
+        2400..=2500 => Some("2.4 GHz"),
+        4900..=5924 => Some("5 GHz"),
+        5925..=7125 => Some("6 GHz"),
+        _ => None,
+    }
+}
+
+// IEEE 802.11 channel number for a frequency in MHz
+pub fn frequency_channel(frequency: u32) -> Option<u32> {
+    match frequency {
+        2484 => Some(14),
+        2412..=2472 => Some((frequency - 2407) / 5),
+        // 6 GHz channel 2 sits below the regular 6 GHz grid
+        5935 => Some(2),
+        5000..=5924 => Some((frequency - 5000) / 5),
+        5950..=7125 => Some((frequency - 5950) / 5),
+        _ => None,
+    }
+}
+
+pub async fn access_point(conn: &Connection, path: OwnedObjectPath) -> NetworkResult<AccessPointInfo> {
+    let ap: AccessPointProxy = proxy_at(conn, &path).await?;
+    Ok(AccessPointInfo {
+        ssid: ap.ssid().await?,
+        strength: ap.strength().await?,
+        flags: ap.flags().await?,
+        wpa_flags: ap.wpa_flags().await?,
+        rsn_flags: ap.rsn_flags().await?,
+        bssid: ap.hw_address().await?,
+        frequency: ap.frequency().await?,
+        max_bitrate: ap.max_bitrate().await?,
+        last_seen: Some(ap.last_seen().await? as i64).filter(|t| *t >= 0),
+        path,
+    })
+}
+
+// Every access point the device currently sees; ones that disappear while
+// being read are skipped
+pub async fn access_points(conn: &Connection, device: &OwnedObjectPath) -> NetworkResult<Vec<AccessPointInfo>> {
+    let wireless: WirelessProxy = proxy_at(conn, device).await?;
+    let mut result = Vec::new();
+    for path in wireless.get_all_access_points().await? {
+        if let Ok(ap) = access_point(conn, path).await {
+            result.push(ap);
+        }
+    }
+    Ok(result)
+}
+
+pub async fn active_access_point(conn: &Connection, device: &OwnedObjectPath) -> NetworkResult<Option<AccessPointInfo>> {
+    let wireless: WirelessProxy = proxy_at(conn, device).await?;
+    let path = wireless.active_access_point().await?;
+    if is_none_path(&path) {
+        return Ok(None);
+    }
+    Ok(Some(access_point(conn, path).await?))
+}
+
+#[derive(Debug, Clone)]
+pub struct SavedConnection {
+    pub path: OwnedObjectPath,
+    pub id: String,
+    pub uuid: String,
+    pub connection_type: String,
+    pub settings: ConnectionSettings,
+}
+
+impl SavedConnection {
+    pub fn setting<T>(&self, group: &str, key: &str) -> Option<T>
+    where
+        T: TryFrom<OwnedValue>,
+    {
+        let value = self.settings.get(group)?.get(key)?.try_clone().ok()?;
+        T::try_from(value).ok()
+    }
+
+    pub fn ssid(&self) -> Option<Vec<u8>> {
+        self.setting("802-11-wireless", "ssid")
+    }
+
+    pub fn is_wifi(&self) -> bool {
+        self.connection_type == "802-11-wireless"
+    }
+
+    // A copy of the settings that can be changed and passed to Update.
+    // GetSettings leaves out secrets, so anything not set again is cleared.
+    pub fn editable_settings(&self) -> NetworkResult<NewConnectionSettings<'_>> {
+        let mut settings = HashMap::new();
+        for (group, values) in &self.settings {
+            let mut group_settings = HashMap::new();
+            for (key, value) in values {
+                group_settings.insert(key.as_str(), Value::try_from(value)?);
+            }
+            settings.insert(group.as_str(), group_settings);
+        }
+        Ok(settings)
+    }
+}
+
+pub async fn saved_connection(conn: &Connection, path: OwnedObjectPath) -> NetworkResult<SavedConnection> {
+    let proxy: SettingsConnectionProxy = proxy_at(conn, &path).await?;
+    let settings = proxy.get_settings().await?;
+
+    let string = |key: &str| -> String {
+        settings
+            .get("connection")
+            .and_then(|c| c.get(key))
+            .and_then(|v| v.try_clone().ok())
+            .and_then(|v| String::try_from(v).ok())
+            .unwrap_or_default()
+    };
+    let (id, uuid, connection_type) = (string("id"), string("uuid"), string("type"));
+
+    Ok(SavedConnection {
+        path,
+        id,
+        uuid,
+        connection_type,
+        settings,
+    })
+}
+
+// Fills the secrets of the groups that have them into `saved.settings`, so an
+// Update built from them doesn't drop stored passwords. Groups without
+// stored secrets are left as they are.
+pub async fn load_secrets(conn: &Connection, saved: &mut SavedConnection) -> NetworkResult<()> {
+    let proxy: SettingsConnectionProxy = proxy_at(conn, &saved.path).await?;
+
+    for group in ["802-11-wireless-security", "802-1x"] {
+        if !saved.settings.contains_key(group) {
+            continue;
+        }
+        let secrets = match proxy.get_secrets(group).await {
+            Ok(secrets) => secrets,
+            Err(zbus::Error::MethodError(name, _, _)) if name.as_str().ends_with(".NoSecrets") => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for (name, values) in secrets {
+            saved.settings.entry(name).or_default().extend(values);
+        }
+    }
+    Ok(())
+}
+
+pub async fn update_connection(conn: &Connection, path: &OwnedObjectPath, settings: NewConnectionSettings<'_>) -> NetworkResult<()> {
+    proxy_at::<SettingsConnectionProxy>(conn, path).await?.update(settings).await?;
+    Ok(())
+}
+
+pub async fn saved_connections(conn: &Connection) -> NetworkResult<Vec<SavedConnection>> {
+    let settings: SettingsProxy = Builder::new(conn).cache_properties(CacheProperties::No).build().await?;
+    let mut result = Vec::new();
+    for path in settings.list_connections().await? {
+        if let Ok(connection) = saved_connection(conn, path).await {
+            result.push(connection);
+        }
+    }
+    Ok(result)
+}
+
+// Saved Wi-Fi connections for `ssid`, matched on the SSID bytes and, for
+// connections created with a custom name, on the name
+pub async fn saved_wifi_connections(conn: &Connection, ssid: &str) -> NetworkResult<Vec<SavedConnection>> {
+    Ok(saved_connections(conn)
+        .await?
+        .into_iter()
+        .filter(|c| c.is_wifi() && (c.ssid().as_deref() == Some(ssid.as_bytes()) || c.id == ssid))
+        .collect())
+}
+
+// Waits until an activation started by ActivateConnection/AddAndActivateConnection
+// either succeeds or fails, translating the device's reason into an error kind
+pub async fn wait_for_activation(
+    conn: &Connection,
+    active: &OwnedObjectPath,
+    device: &OwnedObjectPath,
+    name: &str,
+) -> NetworkResult<()> {
+    let active_connection: ActiveConnectionProxy = proxy_at(conn, active).await?;
+    let deadline = tokio::time::Instant::now() + ACTIVATION_TIMEOUT;
+
+    loop {
+        // The object goes away once the activation has failed
+        let state = active_connection.state().await.unwrap_or(ACTIVE_STATE_DEACTIVATED);
+
+        if state == ACTIVE_STATE_ACTIVATED {
+            return Ok(());
+        }
+        if state == ACTIVE_STATE_DEACTIVATED {
+            let device: DeviceProxy = proxy_at(conn, device).await?;
+            let (_, reason) = device.state_reason().await.unwrap_or((0, 0));
+            return Err(match reason {
+                REASON_NO_SECRETS | REASON_SUPPLICANT_DISCONNECT => NetworkError::new(
+                    NetworkErrorKind::AuthFailed,
+                    format!("Could not authenticate with {}, check the password", name),
+                ),
+                _ => NetworkError::new(
+                    NetworkErrorKind::ActivationFailed,
+                    format!("Failed to connect to {} (reason {})", name, reason),
+                ),
+            });
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Err(NetworkError::new(
+                NetworkErrorKind::Timeout,
+                format!("Timed out connecting to {}", name),
+            ));
+        }
+
+        tokio::time::sleep(ACTIVATION_POLL_INTERVAL).await;
+    }
+}
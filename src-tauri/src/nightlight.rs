@@ -0,0 +1,466 @@
+// Night light: warms the screens on a schedule through hyprsunset, or
+// gammastep where hyprsunset isn't installed. The schedule is either fixed
+// times or sunset to sunrise, computed locally from a latitude and longitude.
+use crate::hyprland::{self, HyprlandClient};
+use crate::app_config_dir;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+const MIN_TEMPERATURE: u32 = 1000;
+// Daylight; hyprsunset and gammastep leave the colors untouched at this value
+const IDENTITY_TEMPERATURE: u32 = 6500;
+const DEFAULT_TEMPERATURE: u32 = 4000;
+const CHECK_INTERVAL_SECS: u64 = 30;
+// How long a freshly started backend gets to fail before it counts as running
+const STARTUP_CHECK_MS: u64 = 200;
+// Sun center 0.833 degrees below the horizon, accounting for refraction and its radius
+const SUN_ZENITH_DEG: f64 = 90.833;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum NightLightSchedule {
+    // Active whenever night light is enabled
+    Always,
+    // Local "HH:MM" times; `start` after `end` spans midnight
+    Fixed { start: String, end: String },
+    // From sunset to sunrise at this location
+    Solar { latitude: f64, longitude: f64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NightLightSettings {
+    pub enabled: bool,
+    // Color temperature in Kelvin while active
+    pub temperature: u32,
+    pub schedule: NightLightSchedule,
+}
+
+impl Default for NightLightSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            temperature: DEFAULT_TEMPERATURE,
+            schedule: NightLightSchedule::Fixed {
+                start: "20:00".to_string(),
+                end: "07:00".to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NightLightStatus {
+    pub settings: NightLightSettings,
+    // Whether the schedule says night light should be on right now
+    pub active: bool,
+    // Today's local sunrise and sunset ("HH:MM") for solar schedules
+    pub sunrise: Option<String>,
+    pub sunset: Option<String>,
+}
+
+// Temperature last sent to the backend (None = identity), and the hyprsunset
+// process when it was started by the app
+#[derive(Default)]
+pub struct NightLightState {
+    applied: Mutex<Option<Option<u32>>>,
+    child: Mutex<Option<Child>>,
+}
+
+fn settings_path() -> PathBuf {
+    app_config_dir().join("night-light.json")
+}
+
+fn load_settings() -> Result<NightLightSettings, String> {
+    let path = settings_path();
+    if !path.exists() {
+        return Ok(NightLightSettings::default());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read night light settings: {}", e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse night light settings: {}", e))
+}
+
+fn store_settings(settings: &NightLightSettings) -> Result<(), String> {
+    let path = settings_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize night light settings: {}", e))?;
+    fs::write(&path, content)
+        .map_err(|e| format!("Failed to write night light settings: {}", e))
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| format!("Invalid time: {} (expected HH:MM)", time))
+}
+
+fn check_temperature(temperature: u32) -> Result<(), String> {
+    if !(MIN_TEMPERATURE..=IDENTITY_TEMPERATURE).contains(&temperature) {
+        return Err(format!(
+            "Temperature must be between {}K and {}K",
+            MIN_TEMPERATURE, IDENTITY_TEMPERATURE
+        ));
+    }
+    Ok(())
+}
+
+fn validate_settings(settings: &NightLightSettings) -> Result<(), String> {
+    check_temperature(settings.temperature)?;
+
+    match &settings.schedule {
+        NightLightSchedule::Always => {}
+        NightLightSchedule::Fixed { start, end } => {
+            if parse_time(start)? == parse_time(end)? {
+                return Err("Start and end time cannot be the same".to_string());
+            }
+        }
+        NightLightSchedule::Solar { latitude, longitude } => {
+            if !(-90.0..=90.0).contains(latitude) {
+                return Err(format!("Latitude must be between -90 and 90, got {}", latitude));
+            }
+            if !(-180.0..=180.0).contains(longitude) {
+                return Err(format!("Longitude must be between -180 and 180, got {}", longitude));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Sunrise and sunset in UTC on the given day, after NOAA's general solar
+// position equations. Err if the sun doesn't set (midnight sun) or doesn't
+// rise (polar night) that day; the bool tells which, true for polar night.
+pub fn sun_times_utc(
+    year: i32,
+    ordinal: u32,
+    latitude: f64,
+    longitude: f64,
+) -> Result<(DateTime<Utc>, DateTime<Utc>), bool> {
+    let days_in_year = if chrono::NaiveDate::from_ymd_opt(year, 12, 31).is_some_and(|d| d.ordinal() == 366) {
+        366.0
+    } else {
+        365.0
+    };
+    // Fractional year in radians, at noon
+    let gamma = 2.0 * std::f64::consts::PI / days_in_year * (ordinal as f64 - 1.0);
+
+    let eqtime = 229.18
+        * (0.000075 + 0.001868 * gamma.cos() - 0.032077 * gamma.sin()
+            - 0.014615 * (2.0 * gamma).cos()
+            - 0.040849 * (2.0 * gamma).sin());
+    let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2.0 * gamma).cos()
+        + 0.000907 * (2.0 * gamma).sin()
+        - 0.002697 * (3.0 * gamma).cos()
+        + 0.00148 * (3.0 * gamma).sin();
+
+    let lat = latitude.to_radians();
+    let cos_hour_angle =
+        SUN_ZENITH_DEG.to_radians().cos() / (lat.cos() * declination.cos()) - lat.tan() * declination.tan();
+    if cos_hour_angle > 1.0 {
+        return Err(true);
+    }
+    if cos_hour_angle < -1.0 {
+        return Err(false);
+    }
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+
+    // Minutes after UTC midnight
+    let sunrise = 720.0 - 4.0 * (longitude + hour_angle) - eqtime;
+    let sunset = 720.0 - 4.0 * (longitude - hour_angle) - eqtime;
+
+    let midnight = Utc
+        .from_utc_datetime(
+            &chrono::NaiveDate::from_yo_opt(year, ordinal)
+                .unwrap_or_default()
+                .and_hms_opt(0, 0, 0)
+                .unwrap_or_default(),
+        );
+    Ok((
+        midnight + Duration::seconds((sunrise * 60.0).round() as i64),
+        midnight + Duration::seconds((sunset * 60.0).round() as i64),
+    ))
+}
+
+fn local_sun_times(now: DateTime<Local>, latitude: f64, longitude: f64) -> Result<(DateTime<Local>, DateTime<Local>), bool> {
+    let (sunrise, sunset) = sun_times_utc(now.year(), now.ordinal(), latitude, longitude)?;
+    Ok((sunrise.with_timezone(&Local), sunset.with_timezone(&Local)))
+}
+
+pub fn is_night(schedule: &NightLightSchedule, now: DateTime<Local>) -> bool {
+    match schedule {
+        NightLightSchedule::Always => true,
+        NightLightSchedule::Fixed { start, end } => {
+            let (Ok(start), Ok(end)) = (parse_time(start), parse_time(end)) else {
+                return false;
+            };
+            let time = now.time().with_second(0).unwrap_or(now.time());
+            if start < end {
+                start <= time && time < end
+            } else {
+                time >= start || time < end
+            }
+        }
+        NightLightSchedule::Solar { latitude, longitude } => match local_sun_times(now, *latitude, *longitude) {
+            Ok((sunrise, sunset)) => now < sunrise || now >= sunset,
+            Err(polar_night) => polar_night,
+        },
+    }
+}
+
+fn hyprsunset_socket_path() -> Option<PathBuf> {
+    let path = hyprland::instance_dir().ok()?.join(".hyprsunset.sock");
+    path.exists().then_some(path)
+}
+
+// Looks the program up in $PATH, like a shell would
+fn command_exists(name: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|path| {
+        std::env::split_paths(&path).any(|dir| {
+            let candidate = dir.join(name);
+            fs::metadata(&candidate).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        })
+    })
+}
+
+// Starts a program that holds the gamma ramp for as long as it runs.
+// Catches the case where it gives up right away, e.g. without gamma control.
+fn spawn_gamma_holder(mut command: Command, name: &str) -> Result<Child, String> {
+    let mut process = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start {}: {}", name, e))?;
+
+    std::thread::sleep(std::time::Duration::from_millis(STARTUP_CHECK_MS));
+    match process.try_wait() {
+        Ok(Some(status)) if !status.success() => Err(format!("{} exited with {}", name, status)),
+        _ => Ok(process),
+    }
+}
+
+// Sets the color temperature, or restores normal colors for None
+fn set_temperature(state: &NightLightState, temperature: Option<u32>) -> Result<(), String> {
+    let mut child = state.child.lock().map_err(|e| e.to_string())?;
+
+    // A running hyprsunset (ours or the user's) is driven over its socket
+    if let Some(socket) = hyprsunset_socket_path() {
+        let client = HyprlandClient::new(socket);
+        let request = match temperature {
+            Some(temperature) => format!("temperature {}", temperature),
+            None => "identity".to_string(),
+        };
+        client
+            .command(&request)
+            .map_err(|e| format!("hyprsunset did not accept the temperature: {}", e))?;
+        return Ok(());
+    }
+
+    // Both backends keep running to hold the adjustment and the compositor
+    // restores normal colors once they exit
+    if let Some(mut previous) = child.take() {
+        let _ = previous.kill();
+        let _ = previous.wait();
+    }
+    let Some(temperature) = temperature else {
+        return Ok(());
+    };
+
+    if command_exists("hyprsunset") {
+        let mut command = Command::new("hyprsunset");
+        command.args(["-t", &temperature.to_string()]);
+        *child = Some(spawn_gamma_holder(command, "hyprsunset")?);
+        return Ok(());
+    }
+
+    if command_exists("gammastep") {
+        let mut command = Command::new("gammastep");
+        command.args(["-P", "-O", &temperature.to_string()]);
+        *child = Some(spawn_gamma_holder(command, "gammastep")?);
+        return Ok(());
+    }
+
+    Err("Neither hyprsunset nor gammastep is installed".to_string())
+}
+
+// Applies what the settings call for now, unless it already is applied
+fn apply_schedule(app_handle: &tauri::AppHandle, settings: &NightLightSettings, force: bool) -> Result<bool, String> {
+    let state = app_handle.state::<NightLightState>();
+    let active = settings.enabled && is_night(&settings.schedule, Local::now());
+    let wanted = active.then_some(settings.temperature);
+
+    let applied = *state.applied.lock().map_err(|e| e.to_string())?;
+    if force || applied != Some(wanted) {
+        set_temperature(&state, wanted)?;
+        *state.applied.lock().map_err(|e| e.to_string())? = Some(wanted);
+
+        if applied.flatten().is_some() != active {
+            if let Err(e) = app_handle.emit("night-light-changed", active) {
+                eprintln!("Failed to emit night-light-changed event: {}", e);
+            }
+        }
+    }
+
+    Ok(active)
+}
+
+// Background task that follows the schedule, checking it every half minute
+pub async fn run_night_light_schedule(app_handle: tauri::AppHandle) -> Result<(), String> {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+
+    loop {
+        interval.tick().await;
+
+        let settings = match load_settings() {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        // Nothing to undo if night light was never turned on
+        if !settings.enabled && app_handle.state::<NightLightState>().applied.lock().map(|a| a.is_none()).unwrap_or(false) {
+            continue;
+        }
+
+        // Starting a backend and socket IPC block, so keep them off the runtime
+        let handle = app_handle.clone();
+        match tauri::async_runtime::spawn_blocking(move || apply_schedule(&handle, &settings, false)).await {
+            Ok(Err(e)) => eprintln!("Failed to apply night light: {}", e),
+            Err(e) => eprintln!("Failed to apply night light: {}", e),
+            Ok(Ok(_)) => {}
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_night_light() -> Result<NightLightStatus, String> {
+    let settings = load_settings()?;
+    let now = Local::now();
+    let active = settings.enabled && is_night(&settings.schedule, now);
+
+    let (sunrise, sunset) = match &settings.schedule {
+        NightLightSchedule::Solar { latitude, longitude } => match local_sun_times(now, *latitude, *longitude) {
+            Ok((sunrise, sunset)) => (
+                Some(sunrise.format("%H:%M").to_string()),
+                Some(sunset.format("%H:%M").to_string()),
+            ),
+            Err(_) => (None, None),
+        },
+        _ => (None, None),
+    };
+
+    Ok(NightLightStatus {
+        settings,
+        active,
+        sunrise,
+        sunset,
+    })
+}
+
+// Starting a backend waits for it to settle, so these commands run it on
+// the blocking pool rather than on the main thread
+#[tauri::command]
+pub async fn set_night_light(app_handle: tauri::AppHandle, settings: NightLightSettings) -> Result<NightLightStatus, String> {
+    validate_settings(&settings)?;
+    store_settings(&settings)?;
+    tauri::async_runtime::spawn_blocking(move || apply_schedule(&app_handle, &settings, true))
+        .await
+        .map_err(|e| format!("Failed to apply night light: {}", e))??;
+    get_night_light()
+}
+
+// Shows a temperature right away; the schedule takes over again on its next check
+#[tauri::command]
+pub async fn preview_night_light_temperature(app_handle: tauri::AppHandle, temperature: u32) -> Result<(), String> {
+    check_temperature(temperature)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app_handle.state::<NightLightState>();
+        set_temperature(&state, Some(temperature))?;
+        // Recorded as applied, so the next check restores the scheduled temperature
+        *state.applied.lock().map_err(|e| e.to_string())? = Some(Some(temperature));
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Failed to preview night light: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LONDON: (f64, f64) = (51.5074, -0.1278);
+    const TROMSO: (f64, f64) = (69.6496, 18.956);
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    fn assert_near(actual: DateTime<Utc>, expected: DateTime<Utc>) {
+        assert!(
+            (actual - expected).num_minutes().abs() <= 3,
+            "expected about {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    fn solar((latitude, longitude): (f64, f64)) -> NightLightSchedule {
+        NightLightSchedule::Solar { latitude, longitude }
+    }
+
+    #[test]
+    fn sun_times_match_published_tables() {
+        // London, summer solstice 2024: sunrise 04:43 BST, sunset 21:21 BST
+        let (sunrise, sunset) = sun_times_utc(2024, 173, LONDON.0, LONDON.1).unwrap();
+        assert_near(sunrise, utc(2024, 6, 21, 3, 43));
+        assert_near(sunset, utc(2024, 6, 21, 20, 21));
+
+        // New York, winter solstice 2024: sunrise 07:17 EST, sunset 16:32 EST
+        let (sunrise, sunset) = sun_times_utc(2024, 356, 40.7128, -74.006).unwrap();
+        assert_near(sunrise, utc(2024, 12, 21, 12, 17));
+        assert_near(sunset, utc(2024, 12, 21, 21, 32));
+    }
+
+    #[test]
+    fn sun_times_report_polar_day_and_night() {
+        assert_eq!(sun_times_utc(2024, 173, TROMSO.0, TROMSO.1), Err(false));
+        assert_eq!(sun_times_utc(2024, 356, TROMSO.0, TROMSO.1), Err(true));
+    }
+
+    // The instants are picked so they fall on the same side of sunrise and
+    // sunset whatever the local time zone of the machine running the tests
+    #[test]
+    fn solar_schedule_follows_the_sun() {
+        assert!(!is_night(&solar(LONDON), utc(2024, 6, 21, 12, 0).with_timezone(&Local)));
+        assert!(is_night(&solar(LONDON), utc(2024, 6, 21, 1, 0).with_timezone(&Local)));
+
+        // Midnight sun and polar night last all day
+        assert!(!is_night(&solar(TROMSO), utc(2024, 6, 21, 12, 0).with_timezone(&Local)));
+        assert!(is_night(&solar(TROMSO), utc(2024, 12, 21, 12, 0).with_timezone(&Local)));
+    }
+
+    #[test]
+    fn fixed_schedule_spans_midnight() {
+        let schedule = NightLightSchedule::Fixed {
+            start: "20:00".to_string(),
+            end: "07:00".to_string(),
+        };
+        let at = |hour, minute| Local.with_ymd_and_hms(2024, 3, 12, hour, minute, 0).unwrap();
+        assert!(is_night(&schedule, at(23, 30)));
+        assert!(is_night(&schedule, at(6, 59)));
+        assert!(!is_night(&schedule, at(7, 0)));
+        assert!(!is_night(&schedule, at(19, 59)));
+    }
+}
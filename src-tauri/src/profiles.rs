@@ -0,0 +1,163 @@
+// Named monitor layouts, keyed by the set of outputs they were saved for.
+// When Hyprland reports a hotplug, the profile matching the connected outputs
+// is applied automatically.
+use crate::monitor_config::write_monitor_config;
+use crate::{apply_monitors_live, app_config_dir, read_hyprland_monitors, Monitor};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorProfile {
+    pub name: String,
+    // Sorted outputs that have to be connected for this profile to match,
+    // by description where Hyprland reports one and by connector name otherwise
+    pub outputs: Vec<String>,
+    pub monitors: Vec<Monitor>,
+}
+
+fn profiles_path() -> PathBuf {
+    app_config_dir().join("monitor-profiles.json")
+}
+
+fn load_profiles() -> Result<Vec<MonitorProfile>, String> {
+    let path = profiles_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read monitor profiles: {}", e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse monitor profiles: {}", e))
+}
+
+fn store_profiles(profiles: &[MonitorProfile]) -> Result<(), String> {
+    let path = profiles_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(profiles)
+        .map_err(|e| format!("Failed to serialize monitor profiles: {}", e))?;
+    fs::write(&path, content)
+        .map_err(|e| format!("Failed to write monitor profiles: {}", e))
+}
+
+// Every connected output, including disabled ones, so that a profile which
+// disables a screen still matches after it has been applied. Virtual outputs
+// come and go with screen sharing and are left out.
+fn connected_outputs() -> Result<Vec<String>, String> {
+    let mut outputs: Vec<String> = read_hyprland_monitors(true)?
+        .into_iter()
+        .filter(|m| !m.is_virtual)
+        .map(|m| if m.description.is_empty() { m.name } else { m.description })
+        .collect();
+    outputs.sort();
+    outputs.dedup();
+    Ok(outputs)
+}
+
+fn activate(profile: &MonitorProfile) -> Result<(), String> {
+    apply_monitors_live(&profile.monitors)?;
+    write_monitor_config(&profile.monitors)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_monitor_profiles() -> Result<Vec<MonitorProfile>, String> {
+    load_profiles()
+}
+
+// Saves the given layout under `name` for the outputs that are connected right now
+#[tauri::command]
+pub fn save_monitor_profile(name: String, monitors: Vec<Monitor>) -> Result<MonitorProfile, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+
+    let profile = MonitorProfile {
+        name,
+        outputs: connected_outputs()?,
+        monitors,
+    };
+
+    // Saving under an existing name replaces that profile. Several profiles
+    // may share an output set; hotplug picks the most recently saved one.
+    let mut profiles = load_profiles()?;
+    profiles.retain(|p| p.name != profile.name);
+    profiles.push(profile.clone());
+    store_profiles(&profiles)?;
+
+    Ok(profile)
+}
+
+#[tauri::command]
+pub fn delete_monitor_profile(name: String) -> Result<String, String> {
+    let mut profiles = load_profiles()?;
+    let count = profiles.len();
+    profiles.retain(|p| p.name != name);
+
+    if profiles.len() == count {
+        return Err(format!("No monitor profile named {}", name));
+    }
+
+    store_profiles(&profiles)?;
+    Ok(format!("Deleted monitor profile {}", name))
+}
+
+#[tauri::command]
+pub fn activate_monitor_profile(name: String) -> Result<String, String> {
+    let profile = load_profiles()?
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| format!("No monitor profile named {}", name))?;
+
+    activate(&profile)?;
+    Ok(format!("Activated monitor profile {}", name))
+}
+
+// Called on monitor hotplug events; applies the profile saved last for the
+// connected outputs, unless it was already applied for this same set. Does
+// blocking IPC and file access, so it runs on the blocking pool; the lock
+// keeps overlapping events from applying a profile twice.
+pub fn handle_monitor_change(app_handle: &tauri::AppHandle, last_outputs: &Mutex<Option<Vec<String>>>) {
+    let Ok(mut last_outputs) = last_outputs.lock() else {
+        return;
+    };
+    let outputs = match connected_outputs() {
+        Ok(outputs) => outputs,
+        Err(e) => {
+            eprintln!("Failed to read connected outputs: {}", e);
+            return;
+        }
+    };
+
+    if last_outputs.as_ref() == Some(&outputs) {
+        return;
+    }
+    *last_outputs = Some(outputs.clone());
+
+    let profile = match load_profiles() {
+        Ok(profiles) => profiles.into_iter().rev().find(|p| p.outputs == outputs),
+        Err(e) => {
+            eprintln!("Failed to load monitor profiles: {}", e);
+            return;
+        }
+    };
+
+    if let Some(profile) = profile {
+        println!("Outputs changed to {:?}, activating monitor profile {}", outputs, profile.name);
+        if let Err(e) = activate(&profile) {
+            eprintln!("Failed to activate monitor profile {}: {}", profile.name, e);
+            return;
+        }
+        if let Err(e) = app_handle.emit("monitor-profile-activated", &profile.name) {
+            eprintln!("Failed to emit monitor-profile-activated event: {}", e);
+        }
+    }
+}
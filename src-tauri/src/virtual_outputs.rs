@@ -0,0 +1,262 @@
+// Headless and nested Wayland outputs created through `output create`, used as
+// a virtual canvas for screen sharing and remote desktop. Hyprland forgets them
+// on restart, so the ones created here are recreated by `exec-once` lines in an
+// autogen file, with their monitor rules saved in monitors.conf, and again when
+// the app starts in case that file isn't sourced.
+use crate::autogen;
+use crate::hyprland::HyprlandClient;
+use crate::layout_validation::is_valid_scale;
+use crate::monitor_config::{format_monitor_rule, remove_monitor_config, write_monitor_config};
+use crate::{app_config_dir, read_hyprland_monitors, Monitor};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+// Name prefixes Hyprland gives outputs created without an explicit name
+const VIRTUAL_PREFIXES: [&str; 2] = ["HEADLESS-", "WAYLAND-"];
+
+const VIRTUAL_OUTPUTS_CONFIG: &str = "virtual-outputs.conf";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputBackend {
+    Headless,
+    // A window on a parent compositor, only available when Hyprland runs nested
+    Wayland,
+}
+
+impl OutputBackend {
+    fn as_str(&self) -> &'static str {
+        match self {
+            OutputBackend::Headless => "headless",
+            OutputBackend::Wayland => "wayland",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualOutput {
+    pub backend: OutputBackend,
+    // Mode, scale and position the output is configured with
+    pub monitor: Monitor,
+}
+
+fn virtual_outputs_path() -> PathBuf {
+    app_config_dir().join("virtual-outputs.json")
+}
+
+fn load_virtual_outputs() -> Result<Vec<VirtualOutput>, String> {
+    let path = virtual_outputs_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read virtual outputs: {}", e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse virtual outputs: {}", e))
+}
+
+fn store_virtual_outputs(outputs: &[VirtualOutput]) -> Result<(), String> {
+    let path = virtual_outputs_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(outputs)
+        .map_err(|e| format!("Failed to serialize virtual outputs: {}", e))?;
+    fs::write(&path, content)
+        .map_err(|e| format!("Failed to write virtual outputs: {}", e))?;
+
+    autogen::write_autogen_file(VIRTUAL_OUTPUTS_CONFIG, &format_virtual_outputs_config(outputs))?;
+    Ok(())
+}
+
+// Creates the outputs when Hyprland starts; their monitor rules in
+// monitors.conf apply as soon as they appear
+fn format_virtual_outputs_config(outputs: &[VirtualOutput]) -> String {
+    let mut config_content = String::from(autogen::AUTOGEN_HEADER);
+    for output in outputs {
+        config_content.push_str(&format!(
+            "exec-once = hyprctl output create {} {}\n",
+            output.backend.as_str(),
+            output.monitor.name
+        ));
+    }
+    config_content
+}
+
+// Outputs named by Hyprland's virtual backends, or created here under a custom name
+pub fn is_virtual_output(name: &str) -> bool {
+    VIRTUAL_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+        || load_virtual_outputs()
+            .map(|outputs| outputs.iter().any(|o| o.monitor.name == name))
+            .unwrap_or(false)
+}
+
+fn check_output_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!(
+            "Invalid output name: {} (use letters, digits, '-' and '_')",
+            name
+        ));
+    }
+    Ok(())
+}
+
+fn output_names() -> Result<Vec<String>, String> {
+    Ok(read_hyprland_monitors(true)?.into_iter().map(|m| m.name).collect())
+}
+
+// Creates the output and returns the name Hyprland gave it
+fn create_output(backend: OutputBackend, name: Option<&str>) -> Result<String, String> {
+    let before = output_names()?;
+    if let Some(name) = name {
+        if before.iter().any(|n| n == name) {
+            return Err(format!("An output named {} already exists", name));
+        }
+    }
+
+    let request = match name {
+        Some(name) => format!("output create {} {}", backend.as_str(), name),
+        None => format!("output create {}", backend.as_str()),
+    };
+    HyprlandClient::from_env()?
+        .batch_commands(&[request])
+        .map_err(|e| format!("Failed to create {} output: {}", backend.as_str(), e))?;
+
+    if let Some(name) = name {
+        return Ok(name.to_string());
+    }
+    output_names()?
+        .into_iter()
+        .find(|n| !before.contains(n))
+        .ok_or_else(|| "Hyprland did not report the new output".to_string())
+}
+
+fn configure_output(monitor: &Monitor) -> Result<(), String> {
+    HyprlandClient::from_env()?
+        .batch_commands(&[format!("keyword monitor {}", format_monitor_rule(monitor))])
+        .map_err(|e| format!("Failed to configure {}: {}", monitor.name, e))
+}
+
+// Recreates the saved outputs that Hyprland doesn't have (after a restart of
+// either Hyprland or the machine) and applies their configuration. One output
+// failing doesn't keep the others from being restored.
+pub fn restore_virtual_outputs() -> Result<(), String> {
+    let outputs = load_virtual_outputs()?;
+    if outputs.is_empty() {
+        return Ok(());
+    }
+
+    let existing = output_names()?;
+    let errors: Vec<String> = outputs
+        .iter()
+        .filter(|o| !existing.contains(&o.monitor.name))
+        .filter_map(|output| {
+            create_output(output.backend, Some(&output.monitor.name))
+                .and_then(|_| configure_output(&output.monitor))
+                .err()
+        })
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+#[tauri::command]
+pub fn list_virtual_outputs() -> Result<Vec<VirtualOutput>, String> {
+    load_virtual_outputs()
+}
+
+// Creates a virtual output, named by Hyprland unless `name` is given, and
+// returns it as Hyprland reports it
+#[tauri::command]
+pub fn create_virtual_output(backend: Option<OutputBackend>, name: Option<String>) -> Result<Monitor, String> {
+    let backend = backend.unwrap_or(OutputBackend::Headless);
+    let name = name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    if let Some(name) = &name {
+        check_output_name(name)?;
+    }
+
+    let name = create_output(backend, name.as_deref())?;
+    let mut monitor = read_hyprland_monitors(true)?
+        .into_iter()
+        .find(|m| m.name == name)
+        .ok_or_else(|| format!("Hyprland did not report {}", name))?;
+    monitor.is_virtual = true;
+
+    let mut outputs = load_virtual_outputs()?;
+    outputs.retain(|o| o.monitor.name != name);
+    outputs.push(VirtualOutput {
+        backend,
+        monitor: monitor.clone(),
+    });
+    store_virtual_outputs(&outputs)?;
+    write_monitor_config(std::slice::from_ref(&monitor))?;
+
+    Ok(monitor)
+}
+
+// Applies a new mode, scale or position to a virtual output and saves it both
+// for recreating the output and as its rule in monitors.conf
+#[tauri::command]
+pub fn configure_virtual_output(monitor: Monitor) -> Result<String, String> {
+    let mut outputs = load_virtual_outputs()?;
+    let output = outputs
+        .iter_mut()
+        .find(|o| o.monitor.name == monitor.name)
+        .ok_or_else(|| format!("{} is not a virtual output", monitor.name))?;
+
+    if !is_valid_scale(monitor.width, monitor.height, monitor.scale) {
+        return Err(format!(
+            "Scale {} does not divide {}x{} into whole pixels",
+            monitor.scale, monitor.width, monitor.height
+        ));
+    }
+
+    let monitor = Monitor {
+        is_virtual: true,
+        match_by_description: false,
+        ..monitor
+    };
+    configure_output(&monitor)?;
+
+    output.monitor = monitor.clone();
+    store_virtual_outputs(&outputs)?;
+    write_monitor_config(std::slice::from_ref(&monitor))?;
+
+    Ok(format!("Configured {}", monitor.name))
+}
+
+#[tauri::command]
+pub fn remove_virtual_output(name: String) -> Result<String, String> {
+    let mut outputs = load_virtual_outputs()?;
+    let count = outputs.len();
+    outputs.retain(|o| o.monitor.name != name);
+
+    // Unsaved outputs created elsewhere (e.g. with hyprctl) can be removed too
+    let exists = output_names()?.contains(&name);
+    if outputs.len() == count && !(exists && is_virtual_output(&name)) {
+        return Err(format!("{} is not a virtual output", name));
+    }
+
+    if exists {
+        HyprlandClient::from_env()?
+            .batch_commands(&[format!("output remove {}", name)])
+            .map_err(|e| format!("Failed to remove {}: {}", name, e))?;
+    }
+
+    store_virtual_outputs(&outputs)?;
+    remove_monitor_config(&name)?;
+
+    Ok(format!("Removed {}", name))
+}
@@ -0,0 +1,449 @@
+// Per-monitor wallpapers, shown by hyprpaper (over its IPC socket) or swww.
+// Assignments are kept in the app config and, for hyprpaper, also written to
+// hyprpaper.conf so they survive a restart of the daemon.
+use crate::autogen::write_atomically;
+use crate::hypr_sources::{escape_comment, hypr_config_dir, strip_comment};
+use crate::hyprland::{self, HyprlandClient, HyprlandError};
+use crate::{app_config_dir, home_dir};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "webp", "jxl", "bmp"];
+// How deep to look below each wallpaper directory
+const MAX_SCAN_DEPTH: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WallpaperBackend {
+    Hyprpaper,
+    Swww,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FitMode {
+    // Scale to fill the monitor, cropping the overflow
+    #[default]
+    Cover,
+    // Scale to fit inside the monitor, leaving borders
+    Contain,
+    // Repeat at the original size
+    Tile,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WallpaperAssignment {
+    // Monitor name from get_monitors; empty applies to every monitor without its own
+    pub monitor: String,
+    pub path: String,
+    #[serde(default)]
+    pub fit: FitMode,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WallpaperSettings {
+    #[serde(default)]
+    pub backend: Option<WallpaperBackend>,
+    #[serde(default)]
+    pub assignments: Vec<WallpaperAssignment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WallpaperFile {
+    pub path: String,
+    pub name: String,
+    pub directory: String,
+}
+
+fn settings_path() -> PathBuf {
+    app_config_dir().join("wallpapers.json")
+}
+
+fn load_settings() -> Result<WallpaperSettings, String> {
+    let path = settings_path();
+    if !path.exists() {
+        return Ok(WallpaperSettings::default());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read wallpaper settings: {}", e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse wallpaper settings: {}", e))
+}
+
+fn store_settings(settings: &WallpaperSettings) -> Result<(), String> {
+    let path = settings_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize wallpaper settings: {}", e))?;
+    fs::write(&path, content)
+        .map_err(|e| format!("Failed to write wallpaper settings: {}", e))
+}
+
+fn default_wallpaper_dirs() -> Vec<PathBuf> {
+    let home = home_dir();
+    vec![
+        home.join("Pictures/Wallpapers"),
+        home.join("Pictures/wallpapers"),
+        home.join("Pictures"),
+        home.join("Wallpapers"),
+        home.join(".local/share/wallpapers"),
+        hypr_config_dir().join("wallpapers"),
+        PathBuf::from("/usr/share/backgrounds"),
+        PathBuf::from("/usr/share/wallpapers"),
+    ]
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.as_str()))
+}
+
+fn scan_images(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            if depth < MAX_SCAN_DEPTH {
+                scan_images(&path, depth + 1, files);
+            }
+        } else if is_image(&path) {
+            files.push(path);
+        }
+    }
+}
+
+pub fn hyprpaper_socket_path() -> Result<PathBuf, String> {
+    Ok(hyprland::instance_dir()?.join(".hyprpaper.sock"))
+}
+
+pub fn hyprpaper_config_path() -> PathBuf {
+    hypr_config_dir().join("hyprpaper.conf")
+}
+
+// hyprpaper if its socket is up, otherwise swww if its daemon answers
+pub fn detect_backend() -> Option<WallpaperBackend> {
+    if hyprpaper_socket_path().is_ok_and(|p| p.exists()) {
+        return Some(WallpaperBackend::Hyprpaper);
+    }
+
+    let swww_running = Command::new("swww")
+        .arg("query")
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    swww_running.then_some(WallpaperBackend::Swww)
+}
+
+// The `MON,[mode:]PATH` argument of hyprpaper's `wallpaper` request and config key
+pub fn format_hyprpaper_wallpaper(assignment: &WallpaperAssignment) -> String {
+    let mode = match assignment.fit {
+        FitMode::Cover => "",
+        FitMode::Contain => "contain:",
+        FitMode::Tile => "tile:",
+    };
+    format!("{},{}{}", assignment.monitor, mode, assignment.path)
+}
+
+pub fn parse_hyprpaper_wallpaper(value: &str) -> Option<WallpaperAssignment> {
+    let (monitor, path) = value.split_once(',')?;
+    let path = path.trim();
+
+    let (fit, path) = if let Some(path) = path.strip_prefix("contain:") {
+        (FitMode::Contain, path)
+    } else if let Some(path) = path.strip_prefix("tile:") {
+        (FitMode::Tile, path)
+    } else {
+        (FitMode::Cover, path)
+    };
+
+    Some(WallpaperAssignment {
+        monitor: monitor.trim().to_string(),
+        path: path.trim().to_string(),
+        fit,
+    })
+}
+
+// The client speaks the same protocol to hyprpaper, but its errors are worded
+// for Hyprland's socket
+fn hyprpaper_error(e: HyprlandError) -> String {
+    match e {
+        HyprlandError::SocketMissing(path) => format!("hyprpaper socket not found at {}", path.display()),
+        HyprlandError::Io(e) => format!("hyprpaper socket error: {}", e),
+        HyprlandError::Command(reply) => format!("hyprpaper rejected the request: {}", reply),
+        HyprlandError::InvalidResponse(e) => format!("Invalid reply from hyprpaper: {}", e),
+        e @ HyprlandError::NotRunning => e.to_string(),
+    }
+}
+
+// Shows the wallpaper through hyprpaper's socket; split out so it can be
+// pointed at any socket
+pub fn apply_hyprpaper(client: &HyprlandClient, assignment: &WallpaperAssignment) -> Result<(), String> {
+    client
+        .command(&format!("preload {}", assignment.path))
+        .map_err(|e| format!("Failed to load {}: {}", assignment.path, hyprpaper_error(e)))?;
+    client
+        .command(&format!("wallpaper {}", format_hyprpaper_wallpaper(assignment)))
+        .map_err(|e| format!("Failed to set the wallpaper: {}", hyprpaper_error(e)))?;
+    // Frees the images no monitor shows anymore; older versions don't know it
+    let _ = client.command("unload unused");
+    Ok(())
+}
+
+fn apply_swww(assignment: &WallpaperAssignment) -> Result<(), String> {
+    // swww has no tiling, showing the image unscaled is the closest
+    let resize = match assignment.fit {
+        FitMode::Cover => "crop",
+        FitMode::Contain => "fit",
+        FitMode::Tile => "no",
+    };
+
+    let mut command = Command::new("swww");
+    command.arg("img").args(["--resize", resize]);
+    if !assignment.monitor.is_empty() {
+        command.args(["--outputs", &assignment.monitor]);
+    }
+    let output = command
+        .arg(&assignment.path)
+        .output()
+        .map_err(|e| format!("Failed to run swww: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "swww failed to set the wallpaper: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+// Rewrites the preload and wallpaper lines of hyprpaper.conf, keeping every
+// other setting (ipc, splash, ...) and comment as it is
+pub fn update_hyprpaper_config(content: &str, assignments: &[WallpaperAssignment]) -> String {
+    let mut lines: Vec<String> = content
+        .lines()
+        .filter(|line| {
+            let key = line.split('=').next().unwrap_or("").trim();
+            !(line.contains('=') && (key == "preload" || key == "wallpaper"))
+        })
+        .map(|line| line.to_string())
+        .collect();
+
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+    if !lines.is_empty() {
+        lines.push(String::new());
+    }
+
+    let mut preloaded: Vec<&str> = Vec::new();
+    for assignment in assignments {
+        if !preloaded.contains(&assignment.path.as_str()) {
+            preloaded.push(&assignment.path);
+            lines.push(format!("preload = {}", escape_comment(&assignment.path)));
+        }
+    }
+    for assignment in assignments {
+        lines.push(format!("wallpaper = {}", escape_comment(&format_hyprpaper_wallpaper(assignment))));
+    }
+
+    let mut config = lines.join("\n");
+    config.push('\n');
+    config
+}
+
+pub fn read_hyprpaper_config(content: &str) -> Vec<WallpaperAssignment> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = strip_comment(line);
+            let (key, value) = line.split_once('=')?;
+            if key.trim() != "wallpaper" {
+                return None;
+            }
+            parse_hyprpaper_wallpaper(value)
+        })
+        .collect()
+}
+
+fn write_hyprpaper_config(assignments: &[WallpaperAssignment]) -> Result<(), String> {
+    let path = hyprpaper_config_path();
+    let content = fs::read_to_string(&path).unwrap_or_default();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    write_atomically(&path, &update_hyprpaper_config(&content, assignments))
+}
+
+fn check_wallpaper_path(path: &str) -> Result<(), String> {
+    if path.contains('\n') || !Path::new(path).is_absolute() {
+        return Err(format!("Invalid wallpaper path: {}", path));
+    }
+    if !Path::new(path).is_file() {
+        return Err(format!("Wallpaper not found: {}", path));
+    }
+    if !is_image(Path::new(path)) {
+        return Err(format!("Unsupported image format: {}", path));
+    }
+    Ok(())
+}
+
+// Images in the given directories, or the usual wallpaper locations
+#[tauri::command]
+pub fn list_wallpapers(directories: Option<Vec<String>>) -> Vec<WallpaperFile> {
+    let directories = match directories {
+        Some(directories) => directories.into_iter().map(PathBuf::from).collect(),
+        None => default_wallpaper_dirs(),
+    };
+
+    let mut files = Vec::new();
+    for dir in &directories {
+        scan_images(dir, 0, &mut files);
+    }
+    files.sort();
+    files.dedup();
+
+    files
+        .into_iter()
+        .map(|path| WallpaperFile {
+            name: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            directory: path.parent().map(|p| p.display().to_string()).unwrap_or_default(),
+            path: path.display().to_string(),
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_wallpaper_backend() -> Option<WallpaperBackend> {
+    detect_backend()
+}
+
+// Saved assignments, falling back to hyprpaper.conf when the app hasn't saved any
+#[tauri::command]
+pub fn get_wallpapers() -> Result<Vec<WallpaperAssignment>, String> {
+    let settings = load_settings()?;
+    if !settings.assignments.is_empty() {
+        return Ok(settings.assignments);
+    }
+
+    let content = fs::read_to_string(hyprpaper_config_path()).unwrap_or_default();
+    Ok(read_hyprpaper_config(&content))
+}
+
+#[tauri::command]
+pub fn set_wallpaper(
+    monitor: String,
+    path: String,
+    fit: Option<FitMode>,
+    backend: Option<WallpaperBackend>,
+) -> Result<String, String> {
+    check_wallpaper_path(&path)?;
+    if monitor.contains(',') || monitor.contains('\n') {
+        return Err(format!("Invalid monitor name: {}", monitor));
+    }
+
+    let mut settings = load_settings()?;
+    let backend = backend
+        .or_else(detect_backend)
+        .or(settings.backend)
+        .ok_or("Neither hyprpaper nor swww is running")?;
+
+    let assignment = WallpaperAssignment {
+        monitor: monitor.trim().to_string(),
+        path,
+        fit: fit.unwrap_or_default(),
+    };
+
+    match backend {
+        WallpaperBackend::Hyprpaper => {
+            apply_hyprpaper(&HyprlandClient::new(hyprpaper_socket_path()?), &assignment)?
+        }
+        WallpaperBackend::Swww => apply_swww(&assignment)?,
+    }
+
+    if settings.assignments.is_empty() {
+        settings.assignments = get_wallpapers()?;
+    }
+    settings.assignments.retain(|a| a.monitor != assignment.monitor);
+    settings.assignments.push(assignment.clone());
+    settings.backend = Some(backend);
+    store_settings(&settings)?;
+
+    // swww caches the last image itself and has no config file
+    if backend == WallpaperBackend::Hyprpaper {
+        write_hyprpaper_config(&settings.assignments)?;
+    }
+
+    let target = if assignment.monitor.is_empty() { "all monitors" } else { &assignment.monitor };
+    Ok(format!("Wallpaper set on {}", target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hyprland::mock_socket;
+
+    #[test]
+    fn applies_wallpaper_over_socket() {
+        let (socket, server) = mock_socket("hyprpaper-apply", &["ok", "ok", "ok"]);
+        let assignment = WallpaperAssignment {
+            monitor: "DP-1".to_string(),
+            path: "/walls/forest.png".to_string(),
+            fit: FitMode::Contain,
+        };
+
+        apply_hyprpaper(&HyprlandClient::new(&socket), &assignment).unwrap();
+
+        let requests = server.join().unwrap();
+        assert_eq!(
+            requests,
+            ["preload /walls/forest.png", "wallpaper DP-1,contain:/walls/forest.png", "unload unused"]
+        );
+        let _ = fs::remove_file(&socket);
+    }
+
+    #[test]
+    fn reports_rejection_as_hyprpaper() {
+        let (socket, server) = mock_socket("hyprpaper-reject", &["ok", "wallpaper failed (not preloaded)"]);
+        let assignment = WallpaperAssignment {
+            monitor: "DP-1".to_string(),
+            path: "/walls/forest.png".to_string(),
+            fit: FitMode::Cover,
+        };
+
+        let error = apply_hyprpaper(&HyprlandClient::new(&socket), &assignment).unwrap_err();
+        assert_eq!(
+            error,
+            "Failed to set the wallpaper: hyprpaper rejected the request: wallpaper failed (not preloaded)"
+        );
+        assert_eq!(server.join().unwrap().len(), 2);
+        let _ = fs::remove_file(&socket);
+    }
+
+    #[test]
+    fn reports_missing_socket_as_hyprpaper() {
+        let socket = std::env::temp_dir().join("archion-missing-hyprpaper.sock");
+        let assignment = WallpaperAssignment {
+            monitor: String::new(),
+            path: "/walls/forest.png".to_string(),
+            fit: FitMode::Cover,
+        };
+
+        let error = apply_hyprpaper(&HyprlandClient::new(&socket), &assignment).unwrap_err();
+        assert!(error.contains("hyprpaper socket not found"), "{}", error);
+    }
+}
@@ -0,0 +1,1085 @@
+// Wi-Fi commands on top of the NetworkManager D-Bus client. Each command is a
+// thin wrapper around a function that takes the bus connection, so the same
+// code runs against a mock NetworkManager on a private bus.
+use crate::networkmanager::{
+    self as nm, AccessPointInfo, ActiveConnectionProxy, NetworkError, NetworkResult, NewConnectionSettings,
+    SavedConnection, SettingsConnectionProxy, WirelessProxy, AP_SEC_KEY_MGMT_PSK, AP_SEC_KEY_MGMT_SAE,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use zbus::zvariant::{OwnedObjectPath, Value};
+use zbus::Connection;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WifiNetwork {
+    pub ssid: String,
+    pub signal_strength: i32,
+    pub security: String,
+    pub connected: bool,
+    pub saved: bool,
+    pub in_use: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WifiStatus {
+    pub enabled: bool,
+    pub connected_ssid: Option<String>,
+    pub interface: String,
+}
+
+pub async fn wifi_status(conn: &Connection) -> NetworkResult<WifiStatus> {
+    let enabled = nm::network_manager(conn).await?.wireless_enabled().await?;
+    let device = nm::wifi_device(conn).await?;
+    let interface = nm::proxy_at::<nm::DeviceProxy>(conn, &device).await?.interface().await?;
+    let connected_ssid = nm::active_access_point(conn, &device)
+        .await?
+        .map(|ap| ap.ssid_string());
+
+    Ok(WifiStatus {
+        enabled,
+        connected_ssid,
+        interface,
+    })
+}
+
+// One entry per SSID, from its strongest access point. Hidden networks
+// (empty SSID) are left out.
+pub async fn wifi_networks(conn: &Connection) -> NetworkResult<Vec<WifiNetwork>> {
+    let device = nm::wifi_device(conn).await?;
+    let access_points = nm::access_points(conn, &device).await?;
+    let active_ssid = nm::active_access_point(conn, &device).await?.map(|ap| ap.ssid);
+    let saved_ssids: Vec<Vec<u8>> = nm::saved_connections(conn)
+        .await?
+        .iter()
+        .filter(|c| c.is_wifi())
+        .filter_map(|c| c.ssid())
+        .collect();
+
+    let mut strongest: Vec<AccessPointInfo> = Vec::new();
+    for ap in access_points.into_iter().filter(|ap| !ap.ssid.is_empty()) {
+        match strongest.iter_mut().find(|s| s.ssid == ap.ssid) {
+            Some(existing) if existing.strength < ap.strength => *existing = ap,
+            Some(_) => {}
+            None => strongest.push(ap),
+        }
+    }
+
+    let mut networks: Vec<WifiNetwork> = strongest
+        .iter()
+        .map(|ap| {
+            let in_use = active_ssid.as_ref() == Some(&ap.ssid);
+            WifiNetwork {
+                ssid: ap.ssid_string(),
+                signal_strength: ap.strength as i32,
+                security: ap.security(),
+                connected: in_use,
+                saved: saved_ssids.contains(&ap.ssid),
+                in_use,
+            }
+        })
+        .collect();
+
+    // Saved networks first, then by signal strength
+    networks.sort_by(|a, b| b.saved.cmp(&a.saved).then(b.signal_strength.cmp(&a.signal_strength)));
+
+    Ok(networks)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WifiAccessPoint {
+    pub bssid: String,
+    pub ssid: String,
+    // MHz
+    pub frequency: u32,
+    pub band: Option<String>,
+    pub channel: Option<u32>,
+    // kbit/s
+    #[serde(rename = "maxBitrate")]
+    pub max_bitrate: u32,
+    pub strength: u8,
+    pub security: String,
+    #[serde(rename = "wpaFlags")]
+    pub wpa_flags: u32,
+    #[serde(rename = "rsnFlags")]
+    pub rsn_flags: u32,
+    // Seconds since a scan last found it
+    #[serde(rename = "lastSeen")]
+    pub last_seen: Option<u64>,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WifiScanNetwork {
+    // Empty for hidden networks
+    pub ssid: String,
+    pub security: String,
+    pub saved: bool,
+    pub connected: bool,
+    // Strongest first
+    #[serde(rename = "accessPoints")]
+    pub access_points: Vec<WifiAccessPoint>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelUsage {
+    pub band: String,
+    pub channel: u32,
+    #[serde(rename = "accessPoints")]
+    pub access_points: usize,
+    // Strongest signal on the channel
+    #[serde(rename = "maxStrength")]
+    pub max_strength: u8,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WifiScan {
+    pub networks: Vec<WifiScanNetwork>,
+    pub channels: Vec<ChannelUsage>,
+}
+
+// Seconds since boot on the clock NetworkManager uses for LastSeen
+fn boot_time_seconds() -> Option<i64> {
+    let mut now = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: clock_gettime only writes the timespec, which lives on our stack
+    let result = unsafe { libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut now) };
+    (result == 0).then_some(now.tv_sec)
+}
+
+// Every access point, grouped by SSID, plus how crowded each channel is
+pub async fn wifi_scan(conn: &Connection) -> NetworkResult<WifiScan> {
+    let device = nm::wifi_device(conn).await?;
+    let access_points = nm::access_points(conn, &device).await?;
+    let active_path = nm::active_access_point(conn, &device).await?.map(|ap| ap.path);
+    let saved_ssids: Vec<Vec<u8>> = nm::saved_connections(conn)
+        .await?
+        .iter()
+        .filter(|c| c.is_wifi())
+        .filter_map(|c| c.ssid())
+        .collect();
+    let now = boot_time_seconds();
+
+    let mut networks: Vec<WifiScanNetwork> = Vec::new();
+    let mut channels: Vec<ChannelUsage> = Vec::new();
+
+    for ap in &access_points {
+        let band = nm::frequency_band(ap.frequency);
+        let channel = nm::frequency_channel(ap.frequency);
+        let active = active_path.as_ref() == Some(&ap.path);
+
+        if let (Some(band), Some(channel)) = (band, channel) {
+            match channels.iter_mut().find(|c| c.band == band && c.channel == channel) {
+                Some(usage) => {
+                    usage.access_points += 1;
+                    usage.max_strength = usage.max_strength.max(ap.strength);
+                }
+                None => channels.push(ChannelUsage {
+                    band: band.to_string(),
+                    channel,
+                    access_points: 1,
+                    max_strength: ap.strength,
+                }),
+            }
+        }
+
+        let entry = WifiAccessPoint {
+            bssid: ap.bssid.clone(),
+            ssid: ap.ssid_string(),
+            frequency: ap.frequency,
+            band: band.map(str::to_string),
+            channel,
+            max_bitrate: ap.max_bitrate,
+            strength: ap.strength,
+            security: ap.security(),
+            wpa_flags: ap.wpa_flags,
+            rsn_flags: ap.rsn_flags,
+            last_seen: ap.last_seen.zip(now).map(|(seen, now)| (now - seen).max(0) as u64),
+            active,
+        };
+
+        match networks.iter_mut().find(|n| n.ssid == entry.ssid) {
+            Some(network) => {
+                network.connected |= active;
+                network.access_points.push(entry);
+            }
+            None => networks.push(WifiScanNetwork {
+                ssid: entry.ssid.clone(),
+                security: entry.security.clone(),
+                saved: !ap.ssid.is_empty() && saved_ssids.contains(&ap.ssid),
+                connected: active,
+                access_points: vec![entry],
+            }),
+        }
+    }
+
+    for network in &mut networks {
+        network.access_points.sort_by_key(|ap| std::cmp::Reverse(ap.strength));
+        network.security = network.access_points[0].security.clone();
+    }
+    networks.sort_by(|a, b| {
+        b.saved
+            .cmp(&a.saved)
+            .then(b.access_points[0].strength.cmp(&a.access_points[0].strength))
+    });
+    channels.sort_by(|a, b| a.band.cmp(&b.band).then(a.channel.cmp(&b.channel)));
+
+    Ok(WifiScan { networks, channels })
+}
+
+// Key management of a personal (non-802.1X) network
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WifiSecurity {
+    Open,
+    Wep,
+    WpaPsk,
+    // WPA3-Personal
+    Sae,
+}
+
+impl WifiSecurity {
+    pub fn of_access_point(ap: &AccessPointInfo) -> Self {
+        if !ap.is_secured() {
+            WifiSecurity::Open
+        } else if ap.wpa_flags == 0 && ap.rsn_flags == 0 {
+            WifiSecurity::Wep
+        } else if ap.rsn_flags & AP_SEC_KEY_MGMT_SAE != 0 && (ap.rsn_flags | ap.wpa_flags) & AP_SEC_KEY_MGMT_PSK == 0 {
+            WifiSecurity::Sae
+        } else {
+            WifiSecurity::WpaPsk
+        }
+    }
+}
+
+fn is_wep_key(password: &str) -> bool {
+    matches!(password.len(), 5 | 13)
+        || (matches!(password.len(), 10 | 26) && password.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+// The 802-11-wireless-security group for `security`, None for open networks
+fn check_passphrase_length(password: &str) -> NetworkResult<()> {
+    if !(8..=63).contains(&password.len()) {
+        return Err(NetworkError::invalid_input("WPA passwords must be 8 to 63 characters long"));
+    }
+    Ok(())
+}
+
+fn security_settings<'a>(
+    security: WifiSecurity,
+    ssid: &str,
+    password: Option<&'a str>,
+) -> NetworkResult<Option<HashMap<&'a str, Value<'a>>>> {
+    let require_password = || password.ok_or_else(|| NetworkError::invalid_input(format!("{} requires a password", ssid)));
+
+    let mut settings = HashMap::new();
+    match security {
+        WifiSecurity::Open => return Ok(None),
+        WifiSecurity::Wep => {
+            let password = require_password()?;
+            settings.insert("key-mgmt", Value::from("none"));
+            settings.insert("wep-key0", Value::from(password));
+            // 1 = key, 2 = passphrase
+            settings.insert("wep-key-type", Value::from(if is_wep_key(password) { 1u32 } else { 2u32 }));
+        }
+        WifiSecurity::WpaPsk => {
+            let password = require_password()?;
+            let is_hex_key = password.len() == 64 && password.chars().all(|c| c.is_ascii_hexdigit());
+            if !is_hex_key {
+                check_passphrase_length(password)?;
+            }
+            settings.insert("key-mgmt", Value::from("wpa-psk"));
+            settings.insert("psk", Value::from(password));
+        }
+        WifiSecurity::Sae => {
+            let password = require_password()?;
+            check_passphrase_length(password)?;
+            settings.insert("key-mgmt", Value::from("sae"));
+            settings.insert("psk", Value::from(password));
+        }
+    }
+    Ok(Some(settings))
+}
+
+// Replaces the key of a saved connection, keeping its other settings and
+// secrets. 802.1X connections have no single key and are refused.
+async fn update_password(conn: &Connection, saved: &mut SavedConnection, password: &str) -> NetworkResult<()> {
+    let key_mgmt = saved.setting::<String>("802-11-wireless-security", "key-mgmt");
+    if key_mgmt.as_deref() == Some("wpa-eap") {
+        return Err(NetworkError::invalid_input(format!(
+            "{} uses WPA-Enterprise; change its 802.1X credentials instead",
+            saved.id
+        )));
+    }
+
+    nm::load_secrets(conn, saved).await?;
+    let mut settings = saved.editable_settings()?;
+
+    let security = settings.entry("802-11-wireless-security").or_default();
+    if key_mgmt.as_deref() == Some("none") {
+        security.insert("wep-key0", Value::from(password));
+    } else {
+        security.entry("key-mgmt").or_insert_with(|| Value::from("wpa-psk"));
+        security.insert("psk", Value::from(password));
+    }
+
+    nm::update_connection(conn, &saved.path, settings).await
+}
+
+// Adds and activates a new connection. Like `nmcli device wifi connect`,
+// the connection is removed again if it never comes up.
+pub(crate) async fn add_and_activate(
+    conn: &Connection,
+    device: &OwnedObjectPath,
+    settings: NewConnectionSettings<'_>,
+    specific_object: &OwnedObjectPath,
+    ssid: &str,
+) -> NetworkResult<String> {
+    let (connection, active) = nm::network_manager(conn)
+        .await?
+        .add_and_activate_connection(settings, device, specific_object)
+        .await?;
+
+    if let Err(e) = nm::wait_for_activation(conn, &active, device, ssid).await {
+        if let Ok(proxy) = nm::proxy_at::<SettingsConnectionProxy>(conn, &connection).await {
+            let _ = proxy.delete().await;
+        }
+        return Err(e);
+    }
+
+    Ok(format!("Connected to {}", ssid))
+}
+
+pub async fn connect(conn: &Connection, ssid: &str, password: Option<&str>) -> NetworkResult<String> {
+    if ssid.is_empty() {
+        return Err(NetworkError::invalid_input("The SSID cannot be empty"));
+    }
+    let device = nm::wifi_device(conn).await?;
+    let ap = nm::access_points(conn, &device)
+        .await?
+        .into_iter()
+        .filter(|ap| ap.ssid == ssid.as_bytes())
+        .max_by_key(|ap| ap.strength);
+
+    connect_to(conn, &device, ssid, ap, password).await
+}
+
+// Connects through one particular access point rather than the strongest
+// one for its SSID
+pub async fn connect_access_point(conn: &Connection, bssid: &str, password: Option<&str>) -> NetworkResult<String> {
+    let device = nm::wifi_device(conn).await?;
+    let ap = nm::access_points(conn, &device)
+        .await?
+        .into_iter()
+        .find(|ap| ap.bssid.eq_ignore_ascii_case(bssid))
+        .ok_or_else(|| NetworkError::not_found(format!("Access point {} not found", bssid)))?;
+    if ap.ssid.is_empty() {
+        return Err(NetworkError::invalid_input(format!("{} does not broadcast its SSID", bssid)));
+    }
+
+    let ssid = ap.ssid_string();
+    connect_to(conn, &device, &ssid, Some(ap), password).await
+}
+
+async fn connect_to(
+    conn: &Connection,
+    device: &OwnedObjectPath,
+    ssid: &str,
+    ap: Option<AccessPointInfo>,
+    password: Option<&str>,
+) -> NetworkResult<String> {
+    let password = password.filter(|p| !p.is_empty());
+    let specific_object = ap
+        .as_ref()
+        .map(|ap| ap.path.clone())
+        .unwrap_or_else(|| nm::none_path().into());
+
+    if let Some(mut saved) = nm::saved_wifi_connections(conn, ssid).await?.into_iter().next() {
+        if let Some(password) = password {
+            update_password(conn, &mut saved, password).await?;
+        }
+        let active = nm::network_manager(conn)
+            .await?
+            .activate_connection(&saved.path, device, &specific_object)
+            .await?;
+        nm::wait_for_activation(conn, &active, device, ssid).await?;
+        return Ok(format!("Connected to {}", ssid));
+    }
+
+    let ap = ap.ok_or_else(|| NetworkError::not_found(format!("Network {} not found", ssid)))?;
+    if ap.is_enterprise() {
+        return Err(NetworkError::invalid_input(format!(
+            "{} uses WPA-Enterprise and needs 802.1X credentials",
+            ssid
+        )));
+    }
+
+    // NetworkManager fills in the rest from the access point
+    let mut settings: NewConnectionSettings = HashMap::new();
+    settings.insert(
+        "802-11-wireless",
+        HashMap::from([("ssid", Value::from(ap.ssid.clone()))]),
+    );
+    if let Some(security) = security_settings(WifiSecurity::of_access_point(&ap), ssid, password)? {
+        settings.insert("802-11-wireless-security", security);
+    }
+
+    add_and_activate(conn, device, settings, &specific_object, ssid).await
+}
+
+// Connects to a network that doesn't broadcast its SSID. The connection is
+// marked hidden so NetworkManager probes for it when autoconnecting.
+pub async fn connect_hidden(
+    conn: &Connection,
+    ssid: &str,
+    security: WifiSecurity,
+    password: Option<&str>,
+) -> NetworkResult<String> {
+    if ssid.is_empty() {
+        return Err(NetworkError::invalid_input("The SSID cannot be empty"));
+    }
+    if ssid.len() > 32 {
+        return Err(NetworkError::invalid_input("SSIDs are at most 32 bytes long"));
+    }
+    let password = password.filter(|p| !p.is_empty());
+    let security_group = security_settings(security, ssid, password)?;
+    let device = nm::wifi_device(conn).await?;
+
+    if let Some(saved) = nm::saved_wifi_connections(conn, ssid).await?.into_iter().next() {
+        let mut settings = saved.editable_settings()?;
+        settings.entry("802-11-wireless").or_default().insert("hidden", Value::from(true));
+        match security_group {
+            Some(group) => settings.insert("802-11-wireless-security", group),
+            None => settings.remove("802-11-wireless-security"),
+        };
+        nm::update_connection(conn, &saved.path, settings).await?;
+
+        let active = nm::network_manager(conn)
+            .await?
+            .activate_connection(&saved.path, &device, &nm::none_path())
+            .await?;
+        nm::wait_for_activation(conn, &active, &device, ssid).await?;
+        return Ok(format!("Connected to {}", ssid));
+    }
+
+    let mut settings: NewConnectionSettings = HashMap::new();
+    settings.insert(
+        "connection",
+        HashMap::from([("id", Value::from(ssid)), ("type", Value::from("802-11-wireless"))]),
+    );
+    settings.insert(
+        "802-11-wireless",
+        HashMap::from([
+            ("ssid", Value::from(ssid.as_bytes().to_vec())),
+            ("mode", Value::from("infrastructure")),
+            ("hidden", Value::from(true)),
+        ]),
+    );
+    if let Some(group) = security_group {
+        settings.insert("802-11-wireless-security", group);
+    }
+
+    add_and_activate(conn, &device, settings, &nm::none_path().into(), ssid).await
+}
+
+// Active Wi-Fi connections for `ssid`, by connection name or SSID
+async fn active_wifi_connections(conn: &Connection, ssid: &str) -> NetworkResult<Vec<OwnedObjectPath>> {
+    let manager = nm::network_manager(conn).await?;
+    let mut result = Vec::new();
+
+    for path in manager.active_connections().await? {
+        let active: ActiveConnectionProxy = nm::proxy_at(conn, &path).await?;
+        if active.connection_type().await.unwrap_or_default() != "802-11-wireless" {
+            continue;
+        }
+        let matches = active.id().await.unwrap_or_default() == ssid
+            || match active.connection().await {
+                Ok(connection) => nm::saved_connection(conn, connection)
+                    .await
+                    .is_ok_and(|c| c.ssid().as_deref() == Some(ssid.as_bytes())),
+                Err(_) => false,
+            };
+        if matches {
+            result.push(path);
+        }
+    }
+
+    Ok(result)
+}
+
+pub async fn disconnect(conn: &Connection, ssid: &str) -> NetworkResult<String> {
+    let active = active_wifi_connections(conn, ssid).await?;
+    if active.is_empty() {
+        return Err(NetworkError::not_found(format!("Not connected to {}", ssid)));
+    }
+
+    let manager = nm::network_manager(conn).await?;
+    for path in active {
+        manager.deactivate_connection(&path).await?;
+    }
+    Ok(format!("Disconnected from {}", ssid))
+}
+
+pub async fn forget(conn: &Connection, ssid: &str) -> NetworkResult<String> {
+    let saved = nm::saved_wifi_connections(conn, ssid).await?;
+    if saved.is_empty() {
+        return Err(NetworkError::not_found(format!("No saved network named {}", ssid)));
+    }
+
+    for connection in saved {
+        nm::proxy_at::<SettingsConnectionProxy>(conn, &connection.path)
+            .await?
+            .delete()
+            .await?;
+    }
+    Ok(format!("Forgot network {}", ssid))
+}
+
+pub async fn rescan(conn: &Connection) -> NetworkResult<String> {
+    let device = nm::wifi_device(conn).await?;
+    let wireless: WirelessProxy = nm::proxy_at(conn, &device).await?;
+    wireless.request_scan(HashMap::new()).await?;
+    Ok("WiFi networks refreshed".to_string())
+}
+
+pub async fn set_wifi_enabled(conn: &Connection, enable: bool) -> NetworkResult<String> {
+    nm::network_manager(conn).await?.set_wireless_enabled(enable).await?;
+    Ok(format!("WiFi turned {}", if enable { "on" } else { "off" }))
+}
+
+#[tauri::command]
+pub async fn get_wifi_status() -> Result<WifiStatus, NetworkError> {
+    wifi_status(&nm::system_bus().await?).await
+}
+
+#[tauri::command]
+pub async fn get_wifi_networks() -> Result<Vec<WifiNetwork>, NetworkError> {
+    wifi_networks(&nm::system_bus().await?).await
+}
+
+#[tauri::command]
+pub async fn get_wifi_scan() -> Result<WifiScan, NetworkError> {
+    wifi_scan(&nm::system_bus().await?).await
+}
+
+// Asks the device to scan; results show up in get_wifi_networks once
+// NetworkManager has them
+#[tauri::command]
+pub async fn refresh_wifi_networks() -> Result<String, NetworkError> {
+    rescan(&nm::system_bus().await?).await
+}
+
+// Connects using the saved connection for `ssid` if there is one (replacing
+// its password when a new one is given), otherwise creates one
+#[tauri::command]
+pub async fn connect_wifi(ssid: String, password: Option<String>) -> Result<String, NetworkError> {
+    connect(&nm::system_bus().await?, &ssid, password.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn connect_wifi_access_point(bssid: String, password: Option<String>) -> Result<String, NetworkError> {
+    connect_access_point(&nm::system_bus().await?, &bssid, password.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn connect_hidden_wifi(
+    ssid: String,
+    security: WifiSecurity,
+    password: Option<String>,
+) -> Result<String, NetworkError> {
+    connect_hidden(&nm::system_bus().await?, &ssid, security, password.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn disconnect_wifi(ssid: String) -> Result<String, NetworkError> {
+    disconnect(&nm::system_bus().await?, &ssid).await
+}
+
+#[tauri::command]
+pub async fn forget_wifi(ssid: String) -> Result<String, NetworkError> {
+    forget(&nm::system_bus().await?, &ssid).await
+}
+
+#[tauri::command]
+pub async fn toggle_wifi(enable: bool) -> Result<String, NetworkError> {
+    set_wifi_enabled(&nm::system_bus().await?, enable).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networkmanager::{ConnectionSettings, NetworkErrorKind, AP_FLAGS_PRIVACY, AP_SEC_KEY_MGMT_802_1X};
+    use std::sync::{Arc, Mutex};
+    use zbus::zvariant::OwnedValue;
+    use zbus::{connection, fdo, interface};
+
+    const ETHERNET_DEVICE: &str = "/org/freedesktop/NetworkManager/Devices/1";
+    const WIFI_DEVICE: &str = "/org/freedesktop/NetworkManager/Devices/2";
+    const ACTIVE_CONNECTION: &str = "/org/freedesktop/NetworkManager/ActiveConnection/1";
+    const CONNECTION_PATHS: [&str; 3] = [
+        "/org/freedesktop/NetworkManager/Settings/1",
+        "/org/freedesktop/NetworkManager/Settings/2",
+        "/org/freedesktop/NetworkManager/Settings/3",
+    ];
+
+    struct MockAccessPoint {
+        ssid: &'static str,
+        strength: u8,
+        key_mgmt: u32,
+        bssid: &'static str,
+    }
+
+    // What the stand-in NetworkManager holds and what it was asked to do
+    #[derive(Default)]
+    struct MockState {
+        // NetworkManager isn't running; calls fail like the bus daemon does
+        stopped: bool,
+        access_points: Vec<MockAccessPoint>,
+        active_access_point: Option<usize>,
+        // The key the access points accept
+        key: String,
+        // Saved connections with their secrets, by path
+        connections: Vec<(String, ConnectionSettings)>,
+        updates: Vec<ConnectionSettings>,
+        deleted: Vec<String>,
+        activated: Vec<String>,
+        active_state: u32,
+        state_reason: u32,
+    }
+
+    type SharedState = Arc<Mutex<MockState>>;
+
+    fn path(path: &str) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(path.to_string()).unwrap()
+    }
+
+    fn owned(value: Value<'_>) -> OwnedValue {
+        OwnedValue::try_from(value).unwrap()
+    }
+
+    fn access_point_path(index: usize) -> String {
+        format!("/org/freedesktop/NetworkManager/AccessPoint/{}", index + 1)
+    }
+
+    fn psk(settings: &ConnectionSettings) -> Option<String> {
+        let value = settings.get("802-11-wireless-security")?.get("psk")?.try_clone().ok()?;
+        String::try_from(value).ok()
+    }
+
+    fn wifi_connection(id: &str, key_mgmt: &str, psk: Option<&str>) -> ConnectionSettings {
+        let mut security = HashMap::from([("key-mgmt".to_string(), owned(Value::from(key_mgmt)))]);
+        if let Some(psk) = psk {
+            security.insert("psk".to_string(), owned(Value::from(psk)));
+        }
+        HashMap::from([
+            (
+                "connection".to_string(),
+                HashMap::from([
+                    ("id".to_string(), owned(Value::from(id))),
+                    ("uuid".to_string(), owned(Value::from(format!("uuid-{}", id)))),
+                    ("type".to_string(), owned(Value::from("802-11-wireless"))),
+                ]),
+            ),
+            (
+                "802-11-wireless".to_string(),
+                HashMap::from([("ssid".to_string(), owned(Value::from(id.as_bytes().to_vec())))]),
+            ),
+            ("802-11-wireless-security".to_string(), security),
+        ])
+    }
+
+    impl MockState {
+        // Activation succeeds only with the right key, failing the way
+        // NetworkManager does when the supplicant rejects it
+        fn activate(&mut self, settings: &ConnectionSettings) {
+            if psk(settings).as_deref() == Some(self.key.as_str()) {
+                self.active_state = 2;
+                self.state_reason = 0;
+            } else {
+                self.active_state = 4;
+                self.state_reason = 7;
+            }
+        }
+    }
+
+    struct MockNetworkManager(SharedState);
+
+    #[interface(name = "org.freedesktop.NetworkManager")]
+    impl MockNetworkManager {
+        fn get_devices(&self) -> fdo::Result<Vec<OwnedObjectPath>> {
+            if self.0.lock().unwrap().stopped {
+                return Err(fdo::Error::ServiceUnknown(
+                    "The name org.freedesktop.NetworkManager was not provided by any .service files".into(),
+                ));
+            }
+            Ok(vec![path(ETHERNET_DEVICE), path(WIFI_DEVICE)])
+        }
+
+        fn activate_connection(
+            &self,
+            connection: OwnedObjectPath,
+            _device: OwnedObjectPath,
+            _specific_object: OwnedObjectPath,
+        ) -> fdo::Result<OwnedObjectPath> {
+            let mut state = self.0.lock().unwrap();
+            let settings = state
+                .connections
+                .iter()
+                .find(|(p, _)| p == connection.as_str())
+                .map(|(_, s)| s.clone())
+                .ok_or_else(|| fdo::Error::UnknownObject(connection.to_string()))?;
+            state.activate(&settings);
+            state.activated.push(connection.to_string());
+            Ok(path(ACTIVE_CONNECTION))
+        }
+
+        fn add_and_activate_connection(
+            &self,
+            settings: ConnectionSettings,
+            _device: OwnedObjectPath,
+            _specific_object: OwnedObjectPath,
+        ) -> (OwnedObjectPath, OwnedObjectPath) {
+            let mut state = self.0.lock().unwrap();
+            let connection = CONNECTION_PATHS[state.connections.len()].to_string();
+            state.activate(&settings);
+            state.connections.push((connection.clone(), settings));
+            (path(&connection), path(ACTIVE_CONNECTION))
+        }
+
+        #[zbus(property)]
+        fn wireless_enabled(&self) -> bool {
+            true
+        }
+    }
+
+    struct MockDevice(SharedState, u32, &'static str);
+
+    #[interface(name = "org.freedesktop.NetworkManager.Device")]
+    impl MockDevice {
+        #[zbus(property)]
+        fn interface(&self) -> String {
+            self.2.to_string()
+        }
+
+        #[zbus(property)]
+        fn device_type(&self) -> u32 {
+            self.1
+        }
+
+        #[zbus(property)]
+        fn state_reason(&self) -> (u32, u32) {
+            (30, self.0.lock().unwrap().state_reason)
+        }
+    }
+
+    struct MockWireless(SharedState);
+
+    #[interface(name = "org.freedesktop.NetworkManager.Device.Wireless")]
+    impl MockWireless {
+        fn get_all_access_points(&self) -> Vec<OwnedObjectPath> {
+            let count = self.0.lock().unwrap().access_points.len();
+            (0..count).map(|i| path(&access_point_path(i))).collect()
+        }
+
+        #[zbus(property)]
+        fn active_access_point(&self) -> OwnedObjectPath {
+            match self.0.lock().unwrap().active_access_point {
+                Some(index) => path(&access_point_path(index)),
+                None => path("/"),
+            }
+        }
+    }
+
+    struct MockAccessPointObject(SharedState, usize);
+
+    impl MockAccessPointObject {
+        fn with<T>(&self, f: impl FnOnce(&MockAccessPoint) -> T) -> T {
+            f(&self.0.lock().unwrap().access_points[self.1])
+        }
+    }
+
+    #[interface(name = "org.freedesktop.NetworkManager.AccessPoint")]
+    impl MockAccessPointObject {
+        #[zbus(property)]
+        fn ssid(&self) -> Vec<u8> {
+            self.with(|ap| ap.ssid.as_bytes().to_vec())
+        }
+
+        #[zbus(property)]
+        fn strength(&self) -> u8 {
+            self.with(|ap| ap.strength)
+        }
+
+        #[zbus(property)]
+        fn flags(&self) -> u32 {
+            AP_FLAGS_PRIVACY
+        }
+
+        #[zbus(property)]
+        fn wpa_flags(&self) -> u32 {
+            0
+        }
+
+        #[zbus(property)]
+        fn rsn_flags(&self) -> u32 {
+            self.with(|ap| ap.key_mgmt)
+        }
+
+        #[zbus(property)]
+        fn hw_address(&self) -> String {
+            self.with(|ap| ap.bssid.to_string())
+        }
+
+        #[zbus(property)]
+        fn frequency(&self) -> u32 {
+            2437
+        }
+
+        #[zbus(property)]
+        fn max_bitrate(&self) -> u32 {
+            54000
+        }
+
+        #[zbus(property)]
+        fn last_seen(&self) -> i32 {
+            -1
+        }
+    }
+
+    struct MockSettings(SharedState);
+
+    #[interface(name = "org.freedesktop.NetworkManager.Settings")]
+    impl MockSettings {
+        fn list_connections(&self) -> Vec<OwnedObjectPath> {
+            self.0.lock().unwrap().connections.iter().map(|(p, _)| path(p)).collect()
+        }
+    }
+
+    struct MockConnection(SharedState, &'static str);
+
+    impl MockConnection {
+        fn settings(&self) -> fdo::Result<ConnectionSettings> {
+            self.0
+                .lock()
+                .unwrap()
+                .connections
+                .iter()
+                .find(|(p, _)| p == self.1)
+                .map(|(_, s)| s.clone())
+                .ok_or_else(|| fdo::Error::UnknownObject(self.1.to_string()))
+        }
+    }
+
+    #[interface(name = "org.freedesktop.NetworkManager.Settings.Connection")]
+    impl MockConnection {
+        // Like NetworkManager, secrets are only handed out by GetSecrets
+        fn get_settings(&self) -> fdo::Result<ConnectionSettings> {
+            let mut settings = self.settings()?;
+            if let Some(security) = settings.get_mut("802-11-wireless-security") {
+                security.remove("psk");
+            }
+            Ok(settings)
+        }
+
+        fn get_secrets(&self, setting_name: String) -> fdo::Result<ConnectionSettings> {
+            let settings = self.settings()?;
+            let mut secrets = HashMap::new();
+            if let Some(psk) = psk(&settings).filter(|_| setting_name == "802-11-wireless-security") {
+                secrets.insert(setting_name, HashMap::from([("psk".to_string(), owned(Value::from(psk)))]));
+            }
+            Ok(secrets)
+        }
+
+        fn update(&self, properties: ConnectionSettings) {
+            let mut state = self.0.lock().unwrap();
+            state.updates.push(properties.clone());
+            if let Some((_, settings)) = state.connections.iter_mut().find(|(p, _)| p == self.1) {
+                *settings = properties;
+            }
+        }
+
+        fn delete(&self) {
+            let mut state = self.0.lock().unwrap();
+            state.connections.retain(|(p, _)| p != self.1);
+            state.deleted.push(self.1.to_string());
+        }
+    }
+
+    struct MockActiveConnection(SharedState);
+
+    #[interface(name = "org.freedesktop.NetworkManager.Connection.Active")]
+    impl MockActiveConnection {
+        #[zbus(property)]
+        fn state(&self) -> u32 {
+            self.0.lock().unwrap().active_state
+        }
+    }
+
+    // Serves the stand-in on one end of a socket pair and returns the client
+    // end, along with the server connection that has to be kept alive
+    async fn mock_network_manager(state: SharedState) -> (Connection, Connection) {
+        let (server_stream, client_stream) = tokio::net::UnixStream::pair().unwrap();
+        let access_point_count = state.lock().unwrap().access_points.len();
+
+        let mut server = connection::Builder::unix_stream(server_stream)
+            .server(zbus::Guid::generate())
+            .unwrap()
+            .p2p()
+            .serve_at("/org/freedesktop/NetworkManager", MockNetworkManager(state.clone()))
+            .unwrap()
+            .serve_at("/org/freedesktop/NetworkManager/Settings", MockSettings(state.clone()))
+            .unwrap()
+            .serve_at(ETHERNET_DEVICE, MockDevice(state.clone(), 1, "eth0"))
+            .unwrap()
+            .serve_at(WIFI_DEVICE, MockDevice(state.clone(), 2, "wlan0"))
+            .unwrap()
+            .serve_at(WIFI_DEVICE, MockWireless(state.clone()))
+            .unwrap()
+            .serve_at(ACTIVE_CONNECTION, MockActiveConnection(state.clone()))
+            .unwrap();
+        for index in 0..access_point_count {
+            server = server
+                .serve_at(access_point_path(index), MockAccessPointObject(state.clone(), index))
+                .unwrap();
+        }
+        for connection_path in CONNECTION_PATHS {
+            server = server
+                .serve_at(connection_path, MockConnection(state.clone(), connection_path))
+                .unwrap();
+        }
+
+        let server = tokio::spawn(server.build());
+        let client = connection::Builder::unix_stream(client_stream).p2p().build().await.unwrap();
+        (server.await.unwrap().unwrap(), client)
+    }
+
+    fn with_mock<F, Fut>(state: MockState, test: F) -> SharedState
+    where
+        F: FnOnce(Connection) -> Fut,
+        Fut: std::future::Future<Output = ()>,
+    {
+        let state = Arc::new(Mutex::new(state));
+        let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+        runtime.block_on(async {
+            let (_server, client) = mock_network_manager(state.clone()).await;
+            test(client).await;
+        });
+        state
+    }
+
+    fn cafe_network() -> MockState {
+        MockState {
+            access_points: vec![
+                MockAccessPoint {
+                    ssid: "Cafe:Wifi",
+                    strength: 40,
+                    key_mgmt: AP_SEC_KEY_MGMT_PSK,
+                    bssid: "AA:AA:AA:AA:AA:01",
+                },
+                MockAccessPoint {
+                    ssid: "Cafe:Wifi",
+                    strength: 75,
+                    key_mgmt: AP_SEC_KEY_MGMT_PSK,
+                    bssid: "AA:AA:AA:AA:AA:02",
+                },
+                MockAccessPoint {
+                    ssid: "Campus",
+                    strength: 60,
+                    key_mgmt: AP_SEC_KEY_MGMT_802_1X,
+                    bssid: "BB:BB:BB:BB:BB:01",
+                },
+            ],
+            key: "espresso".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn ssid_with_colon_is_kept_whole() {
+        let mut state = cafe_network();
+        state.active_access_point = Some(1);
+        state.connections.push((
+            CONNECTION_PATHS[0].to_string(),
+            wifi_connection("Cafe:Wifi", "wpa-psk", Some("espresso")),
+        ));
+
+        let state = with_mock(state, |conn| async move {
+            let networks = wifi_networks(&conn).await.unwrap();
+            let cafe = networks.iter().find(|n| n.ssid == "Cafe:Wifi").unwrap();
+            assert_eq!(cafe.signal_strength, 75);
+            assert!(cafe.saved && cafe.connected);
+            assert_eq!(networks.len(), 2);
+
+            let status = wifi_status(&conn).await.unwrap();
+            assert_eq!(status.connected_ssid.as_deref(), Some("Cafe:Wifi"));
+            assert_eq!(status.interface, "wlan0");
+
+            assert_eq!(connect(&conn, "Cafe:Wifi", None).await.unwrap(), "Connected to Cafe:Wifi");
+        });
+        assert_eq!(state.lock().unwrap().activated, vec![CONNECTION_PATHS[0]]);
+    }
+
+    #[test]
+    fn wrong_key_is_auth_failed() {
+        let state = with_mock(cafe_network(), |conn| async move {
+            let error = connect(&conn, "Cafe:Wifi", Some("decaffeinated")).await.unwrap_err();
+            assert_eq!(error.kind, NetworkErrorKind::AuthFailed);
+
+            assert_eq!(connect(&conn, "Cafe:Wifi", Some("espresso")).await.unwrap(), "Connected to Cafe:Wifi");
+        });
+
+        // The connection made for the wrong key isn't left behind
+        let state = state.lock().unwrap();
+        assert_eq!(state.deleted, vec![CONNECTION_PATHS[0]]);
+        assert_eq!(state.connections.len(), 1);
+    }
+
+    #[test]
+    fn new_key_replaces_saved_one() {
+        let mut state = cafe_network();
+        state.connections.push((
+            CONNECTION_PATHS[0].to_string(),
+            wifi_connection("Cafe:Wifi", "wpa-psk", Some("latte")),
+        ));
+        state.connections.push((CONNECTION_PATHS[1].to_string(), wifi_connection("Campus", "wpa-eap", None)));
+
+        let state = with_mock(state, |conn| async move {
+            connect(&conn, "Cafe:Wifi", Some("espresso")).await.unwrap();
+
+            let error = connect(&conn, "Campus", Some("hunter2")).await.unwrap_err();
+            assert_eq!(error.kind, NetworkErrorKind::InvalidInput);
+        });
+
+        let state = state.lock().unwrap();
+        assert_eq!(state.updates.len(), 1);
+        assert_eq!(psk(&state.updates[0]).as_deref(), Some("espresso"));
+    }
+
+    #[test]
+    fn stopped_networkmanager_is_unavailable() {
+        let state = MockState {
+            stopped: true,
+            ..cafe_network()
+        };
+        with_mock(state, |conn| async move {
+            let error = wifi_networks(&conn).await.unwrap_err();
+            assert_eq!(error.kind, NetworkErrorKind::Unavailable);
+        });
+    }
+
+    #[test]
+    fn wpa_passwords_need_8_to_63_characters() {
+        let hex_key = "0123456789abcdef".repeat(4);
+        let long = "x".repeat(64);
+        for security in [WifiSecurity::WpaPsk, WifiSecurity::Sae] {
+            for rejected in ["decaf", "", &long] {
+                let error = security_settings(security, "Cafe", Some(rejected)).unwrap_err();
+                assert_eq!(error.kind, NetworkErrorKind::InvalidInput, "{:?} {:?}", security, rejected);
+            }
+            assert!(security_settings(security, "Cafe", Some("espresso")).is_ok());
+            assert!(security_settings(security, "Cafe", None).is_err());
+        }
+        // Only WPA-PSK takes a raw 64 digit hex key
+        assert!(security_settings(WifiSecurity::WpaPsk, "Cafe", Some(&hex_key)).is_ok());
+        assert!(security_settings(WifiSecurity::Sae, "Cafe", Some(&hex_key)).is_err());
+    }
+}
@@ -0,0 +1,505 @@
+// Editor for saved Wi-Fi connections: IPv4/IPv6 addressing and DNS,
+// autoconnect, metered and MAC address settings. Changes are validated here
+// before the connection is updated in NetworkManager.
+use crate::networkmanager::{self as nm, NetworkError, NetworkResult, SavedConnection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use zbus::zvariant::Value;
+use zbus::Connection;
+
+const IPV4_METHODS: &[&str] = &["auto", "manual", "link-local", "shared", "disabled"];
+const IPV6_METHODS: &[&str] = &["auto", "dhcp", "manual", "link-local", "shared", "ignore", "disabled"];
+// Keywords for 802-11-wireless.assigned-mac-address; anything else must be a MAC address
+const MAC_KEYWORDS: &[&str] = &["default", "permanent", "preserve", "random", "stable"];
+const MAX_AUTOCONNECT_PRIORITY: i32 = 999;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metered {
+    // Let NetworkManager guess, e.g. from the DHCP vendor options of a phone hotspot
+    Unknown,
+    Yes,
+    No,
+}
+
+impl Metered {
+    // NMMetered
+    fn from_nm(value: i32) -> Self {
+        match value {
+            1 | 3 => Metered::Yes,
+            2 | 4 => Metered::No,
+            _ => Metered::Unknown,
+        }
+    }
+
+    fn to_nm(self) -> i32 {
+        match self {
+            Metered::Unknown => 0,
+            Metered::Yes => 1,
+            Metered::No => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpSettings {
+    // "auto", "manual", ... (see IPV4_METHODS and IPV6_METHODS)
+    pub method: String,
+    // Static addresses with prefix length, e.g. "192.168.1.20/24"
+    pub addresses: Vec<String>,
+    pub gateway: Option<String>,
+    pub dns: Vec<String>,
+    #[serde(rename = "dnsSearch")]
+    pub dns_search: Vec<String>,
+    // Use only the DNS servers above, not the ones from DHCP or router advertisements
+    #[serde(rename = "ignoreAutoDns")]
+    pub ignore_auto_dns: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WifiConnectionSettings {
+    pub uuid: String,
+    // Connection name
+    pub id: String,
+    // Informational, not changed when saving
+    pub ssid: String,
+    pub autoconnect: bool,
+    #[serde(rename = "autoconnectPriority")]
+    pub autoconnect_priority: i32,
+    pub metered: Metered,
+    // "default", "permanent", "preserve", "random", "stable" or a fixed MAC address
+    #[serde(rename = "macAddress")]
+    pub mac_address: String,
+    pub ipv4: IpSettings,
+    pub ipv6: IpSettings,
+}
+
+fn address_data(saved: &SavedConnection, group: &str) -> Vec<String> {
+    let Some(Value::Array(entries)) = saved.settings.get(group).and_then(|g| g.get("address-data")).map(|v| &**v) else {
+        return Vec::new();
+    };
+
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let Value::Dict(entry) = entry else {
+                return None;
+            };
+            let address: String = entry.get::<&str, String>(&"address").ok()??;
+            let prefix: u32 = entry.get::<&str, u32>(&"prefix").ok()??;
+            Some(format!("{}/{}", address, prefix))
+        })
+        .collect()
+}
+
+// Newer NetworkManager versions list DNS servers as strings in dns-data; the
+// older dns property holds IPv4 addresses as integers whose bytes are in
+// network order (an in_addr_t, hence from_be/to_be) and IPv6 addresses as
+// byte arrays
+fn dns_servers(saved: &SavedConnection, group: &str, ipv6: bool) -> Vec<String> {
+    if let Some(servers) = saved.setting::<Vec<String>>(group, "dns-data") {
+        return servers;
+    }
+    if ipv6 {
+        saved.setting::<Vec<Vec<u8>>>(group, "dns")
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|bytes| <[u8; 16]>::try_from(bytes).ok())
+            .map(|bytes| Ipv6Addr::from(bytes).to_string())
+            .collect()
+    } else {
+        saved.setting::<Vec<u32>>(group, "dns")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|address| Ipv4Addr::from(u32::from_be(address)).to_string())
+            .collect()
+    }
+}
+
+fn ip_settings(saved: &SavedConnection, group: &str, ipv6: bool) -> IpSettings {
+    IpSettings {
+        method: saved.setting::<String>(group, "method").unwrap_or_else(|| "auto".to_string()),
+        addresses: address_data(saved, group),
+        gateway: saved.setting::<String>(group, "gateway").filter(|g| !g.is_empty()),
+        dns: dns_servers(saved, group, ipv6),
+        dns_search: saved.setting::<Vec<String>>(group, "dns-search").unwrap_or_default(),
+        ignore_auto_dns: saved.setting::<bool>(group, "ignore-auto-dns").unwrap_or(false),
+    }
+}
+
+pub fn connection_settings(saved: &SavedConnection) -> WifiConnectionSettings {
+    WifiConnectionSettings {
+        uuid: saved.uuid.clone(),
+        id: saved.id.clone(),
+        ssid: String::from_utf8_lossy(&saved.ssid().unwrap_or_default()).to_string(),
+        autoconnect: saved.setting::<bool>("connection", "autoconnect").unwrap_or(true),
+        autoconnect_priority: saved.setting::<i32>("connection", "autoconnect-priority").unwrap_or(0),
+        metered: Metered::from_nm(saved.setting::<i32>("connection", "metered").unwrap_or(0)),
+        mac_address: saved.setting::<String>("802-11-wireless", "assigned-mac-address")
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| "default".to_string()),
+        ipv4: ip_settings(saved, "ipv4", false),
+        ipv6: ip_settings(saved, "ipv6", true),
+    }
+}
+
+fn is_mac_address(value: &str) -> bool {
+    let parts: Vec<&str> = value.split(':').collect();
+    parts.len() == 6 && parts.iter().all(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn is_domain(value: &str) -> bool {
+    let value = value.strip_prefix('~').unwrap_or(value).trim_end_matches('.');
+    !value.is_empty()
+        && value.len() <= 253
+        && value.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+        })
+}
+
+fn parse_address(value: &str, ipv6: bool) -> Option<(IpAddr, u32)> {
+    let (address, prefix) = value.trim().split_once('/')?;
+    let address: IpAddr = address.parse().ok()?;
+    let prefix: u32 = prefix.parse().ok()?;
+    let max_prefix = if ipv6 { 128 } else { 32 };
+    (address.is_ipv6() == ipv6 && prefix <= max_prefix).then_some((address, prefix))
+}
+
+fn parse_ip(value: &str, ipv6: bool) -> Option<IpAddr> {
+    value.trim().parse::<IpAddr>().ok().filter(|a| a.is_ipv6() == ipv6)
+}
+
+fn validate_ip_settings(ip: &IpSettings, ipv6: bool) -> NetworkResult<()> {
+    let family = if ipv6 { "IPv6" } else { "IPv4" };
+    let methods = if ipv6 { IPV6_METHODS } else { IPV4_METHODS };
+    if !methods.contains(&ip.method.as_str()) {
+        return Err(NetworkError::invalid_input(format!("Unknown {} method: {}", family, ip.method)));
+    }
+
+    if ip.method == "manual" && ip.addresses.is_empty() {
+        return Err(NetworkError::invalid_input(format!("Manual {} needs at least one address", family)));
+    }
+    let is_off = matches!(ip.method.as_str(), "disabled" | "ignore");
+    if is_off && (!ip.addresses.is_empty() || ip.gateway.is_some() || !ip.dns.is_empty()) {
+        return Err(NetworkError::invalid_input(format!(
+            "{} is {}, remove its addresses, gateway and DNS servers",
+            family, ip.method
+        )));
+    }
+
+    for address in &ip.addresses {
+        if parse_address(address, ipv6).is_none() {
+            return Err(NetworkError::invalid_input(format!(
+                "Invalid {} address: {} (expected address/prefix)",
+                family, address
+            )));
+        }
+    }
+    if let Some(gateway) = &ip.gateway {
+        if parse_ip(gateway, ipv6).is_none() {
+            return Err(NetworkError::invalid_input(format!("Invalid {} gateway: {}", family, gateway)));
+        }
+        if ip.addresses.is_empty() {
+            return Err(NetworkError::invalid_input(format!(
+                "An {} gateway needs a static address",
+                family
+            )));
+        }
+    }
+    for server in &ip.dns {
+        if parse_ip(server, ipv6).is_none() {
+            return Err(NetworkError::invalid_input(format!("Invalid {} DNS server: {}", family, server)));
+        }
+    }
+    for domain in &ip.dns_search {
+        if !is_domain(domain.trim()) {
+            return Err(NetworkError::invalid_input(format!("Invalid search domain: {}", domain)));
+        }
+    }
+    if ip.ignore_auto_dns && ip.dns.is_empty() && !is_off {
+        return Err(NetworkError::invalid_input(format!(
+            "Add a DNS server when ignoring the automatic {} DNS servers",
+            family
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn validate_connection_settings(settings: &WifiConnectionSettings) -> NetworkResult<()> {
+    if settings.id.trim().is_empty() {
+        return Err(NetworkError::invalid_input("The connection name cannot be empty"));
+    }
+    if settings.autoconnect_priority.abs() > MAX_AUTOCONNECT_PRIORITY {
+        return Err(NetworkError::invalid_input(format!(
+            "Autoconnect priority must be between -{0} and {0}",
+            MAX_AUTOCONNECT_PRIORITY
+        )));
+    }
+    let mac = settings.mac_address.trim();
+    if !MAC_KEYWORDS.contains(&mac) && !is_mac_address(mac) {
+        return Err(NetworkError::invalid_input(format!("Invalid MAC address setting: {}", mac)));
+    }
+    if settings.ipv4.method == "disabled" && matches!(settings.ipv6.method.as_str(), "disabled" | "ignore") {
+        return Err(NetworkError::invalid_input("IPv4 and IPv6 cannot both be disabled"));
+    }
+
+    validate_ip_settings(&settings.ipv4, false)?;
+    validate_ip_settings(&settings.ipv6, true)
+}
+
+// Writes `ip` into an ipv4/ipv6 group, dropping the deprecated properties
+// that would otherwise take precedence over (or conflict with) the new values
+fn apply_ip_settings(group: &mut HashMap<&str, Value<'_>>, ip: &IpSettings, ipv6: bool) {
+    for key in ["addresses", "dns-data"] {
+        group.remove(key);
+    }
+
+    let addresses: Vec<HashMap<&str, Value>> = ip
+        .addresses
+        .iter()
+        .filter_map(|a| parse_address(a, ipv6))
+        .map(|(address, prefix)| {
+            HashMap::from([
+                ("address", Value::from(address.to_string())),
+                ("prefix", Value::from(prefix)),
+            ])
+        })
+        .collect();
+    group.insert("method", Value::from(ip.method.clone()));
+    group.insert("address-data", Value::from(addresses));
+    match &ip.gateway {
+        Some(gateway) => group.insert("gateway", Value::from(gateway.trim().to_string())),
+        None => group.remove("gateway"),
+    };
+
+    let servers = ip.dns.iter().filter_map(|s| parse_ip(s, ipv6));
+    let dns = if ipv6 {
+        Value::from(
+            servers
+                .filter_map(|s| match s {
+                    IpAddr::V6(s) => Some(s.octets().to_vec()),
+                    IpAddr::V4(_) => None,
+                })
+                .collect::<Vec<Vec<u8>>>(),
+        )
+    } else {
+        Value::from(
+            servers
+                .filter_map(|s| match s {
+                    IpAddr::V4(s) => Some(u32::from(s).to_be()),
+                    IpAddr::V6(_) => None,
+                })
+                .collect::<Vec<u32>>(),
+        )
+    };
+    group.insert("dns", dns);
+    group.insert(
+        "dns-search",
+        Value::from(ip.dns_search.iter().map(|d| d.trim().to_string()).collect::<Vec<String>>()),
+    );
+    group.insert("ignore-auto-dns", Value::from(ip.ignore_auto_dns));
+}
+
+async fn saved_wifi_connection(conn: &Connection, uuid: &str) -> NetworkResult<SavedConnection> {
+    nm::saved_connections(conn)
+        .await?
+        .into_iter()
+        .find(|c| c.is_wifi() && c.uuid == uuid)
+        .ok_or_else(|| NetworkError::not_found(format!("No saved Wi-Fi connection with UUID {}", uuid)))
+}
+
+pub async fn list_connections(conn: &Connection) -> NetworkResult<Vec<WifiConnectionSettings>> {
+    let mut connections: Vec<WifiConnectionSettings> = nm::saved_connections(conn)
+        .await?
+        .iter()
+        .filter(|c| c.is_wifi())
+        .map(connection_settings)
+        .collect();
+    connections.sort_by_key(|c| c.id.to_lowercase());
+    Ok(connections)
+}
+
+pub async fn save_connection(conn: &Connection, settings: &WifiConnectionSettings) -> NetworkResult<String> {
+    validate_connection_settings(settings)?;
+
+    let mut saved = saved_wifi_connection(conn, &settings.uuid).await?;
+    nm::load_secrets(conn, &mut saved).await?;
+    let mut update = saved.editable_settings()?;
+
+    let connection = update.entry("connection").or_default();
+    connection.insert("id", Value::from(settings.id.trim().to_string()));
+    connection.insert("autoconnect", Value::from(settings.autoconnect));
+    connection.insert("autoconnect-priority", Value::from(settings.autoconnect_priority));
+    connection.insert("metered", Value::from(settings.metered.to_nm()));
+
+    let wireless = update.entry("802-11-wireless").or_default();
+    // The byte array form of the same setting, which NetworkManager still returns
+    wireless.remove("cloned-mac-address");
+    match settings.mac_address.trim() {
+        "default" => wireless.remove("assigned-mac-address"),
+        mac => wireless.insert("assigned-mac-address", Value::from(mac.to_string())),
+    };
+
+    apply_ip_settings(update.entry("ipv4").or_default(), &settings.ipv4, false);
+    apply_ip_settings(update.entry("ipv6").or_default(), &settings.ipv6, true);
+
+    nm::update_connection(conn, &saved.path, update).await?;
+    Ok(format!("Saved {}, reconnect to apply", settings.id.trim()))
+}
+
+#[tauri::command]
+pub async fn list_saved_wifi_connections() -> Result<Vec<WifiConnectionSettings>, NetworkError> {
+    list_connections(&nm::system_bus().await?).await
+}
+
+#[tauri::command]
+pub async fn get_wifi_connection(uuid: String) -> Result<WifiConnectionSettings, NetworkError> {
+    let conn = nm::system_bus().await?;
+    Ok(connection_settings(&saved_wifi_connection(&conn, &uuid).await?))
+}
+
+#[tauri::command]
+pub fn validate_wifi_connection(settings: WifiConnectionSettings) -> Result<(), NetworkError> {
+    validate_connection_settings(&settings)
+}
+
+#[tauri::command]
+pub async fn save_wifi_connection(settings: WifiConnectionSettings) -> Result<String, NetworkError> {
+    save_connection(&nm::system_bus().await?, &settings).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networkmanager::NetworkErrorKind;
+    use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+    fn ip(method: &str) -> IpSettings {
+        IpSettings {
+            method: method.to_string(),
+            addresses: Vec::new(),
+            gateway: None,
+            dns: Vec::new(),
+            dns_search: Vec::new(),
+            ignore_auto_dns: false,
+        }
+    }
+
+    fn settings() -> WifiConnectionSettings {
+        WifiConnectionSettings {
+            uuid: "8a5a0e42-3c8c-4c1b-9d0e-5f3a1c2b4d6e".to_string(),
+            id: "Cafe".to_string(),
+            ssid: "Cafe".to_string(),
+            autoconnect: true,
+            autoconnect_priority: 0,
+            metered: Metered::Unknown,
+            mac_address: "default".to_string(),
+            ipv4: ip("auto"),
+            ipv6: ip("auto"),
+        }
+    }
+
+    // A saved connection holding what apply_ip_settings wrote into `group`
+    fn saved_with(group: &str, values: HashMap<&str, Value<'_>>) -> SavedConnection {
+        let values = values
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), OwnedValue::try_from(value).unwrap()))
+            .collect();
+        SavedConnection {
+            path: OwnedObjectPath::try_from("/org/freedesktop/NetworkManager/Settings/1").unwrap(),
+            id: "Cafe".to_string(),
+            uuid: settings().uuid,
+            connection_type: "802-11-wireless".to_string(),
+            settings: HashMap::from([(group.to_string(), values)]),
+        }
+    }
+
+    fn rejected(settings: &WifiConnectionSettings) -> String {
+        let error = validate_connection_settings(settings).unwrap_err();
+        assert_eq!(error.kind, NetworkErrorKind::InvalidInput, "{}", error.message);
+        error.message
+    }
+
+    #[test]
+    fn static_ipv4_round_trips() {
+        let ipv4 = IpSettings {
+            method: "manual".to_string(),
+            addresses: vec!["192.168.1.20/24".to_string()],
+            gateway: Some("192.168.1.1".to_string()),
+            dns: vec!["192.168.1.1".to_string(), "9.9.9.9".to_string()],
+            dns_search: vec!["home.arpa".to_string()],
+            ignore_auto_dns: true,
+        };
+        let mut group = HashMap::new();
+        apply_ip_settings(&mut group, &ipv4, false);
+
+        // The legacy property holds each address with its bytes in network order
+        let dns = <Vec<u32>>::try_from(group["dns"].try_clone().unwrap()).unwrap();
+        assert_eq!(dns.iter().map(|d| d.to_ne_bytes()).collect::<Vec<_>>(), [[192, 168, 1, 1], [9, 9, 9, 9]]);
+
+        let read = ip_settings(&saved_with("ipv4", group), "ipv4", false);
+        assert_eq!(read.method, "manual");
+        assert_eq!(read.addresses, ipv4.addresses);
+        assert_eq!(read.gateway, ipv4.gateway);
+        assert_eq!(read.dns, ipv4.dns);
+        assert_eq!(read.dns_search, ipv4.dns_search);
+        assert!(read.ignore_auto_dns);
+    }
+
+    #[test]
+    fn ipv6_dns_round_trips() {
+        let ipv6 = IpSettings {
+            dns: vec!["2620:fe::fe".to_string()],
+            ..ip("auto")
+        };
+        let mut group = HashMap::new();
+        apply_ip_settings(&mut group, &ipv6, true);
+
+        assert_eq!(ip_settings(&saved_with("ipv6", group), "ipv6", true).dns, ipv6.dns);
+    }
+
+    #[test]
+    fn rejects_inconsistent_ip_settings() {
+        assert!(validate_connection_settings(&settings()).is_ok());
+
+        let both_off = WifiConnectionSettings {
+            ipv4: ip("disabled"),
+            ipv6: ip("ignore"),
+            ..settings()
+        };
+        assert_eq!(rejected(&both_off), "IPv4 and IPv6 cannot both be disabled");
+
+        let gateway_only = WifiConnectionSettings {
+            ipv4: IpSettings {
+                gateway: Some("192.168.1.1".to_string()),
+                ..ip("auto")
+            },
+            ..settings()
+        };
+        assert_eq!(rejected(&gateway_only), "An IPv4 gateway needs a static address");
+
+        let no_dns = WifiConnectionSettings {
+            ipv6: IpSettings {
+                ignore_auto_dns: true,
+                ..ip("auto")
+            },
+            ..settings()
+        };
+        assert_eq!(rejected(&no_dns), "Add a DNS server when ignoring the automatic IPv6 DNS servers");
+
+        let wrong_family = WifiConnectionSettings {
+            ipv4: IpSettings {
+                dns: vec!["2620:fe::fe".to_string()],
+                ..ip("auto")
+            },
+            ..settings()
+        };
+        assert_eq!(rejected(&wrong_family), "Invalid IPv4 DNS server: 2620:fe::fe");
+    }
+}
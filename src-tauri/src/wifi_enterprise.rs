@@ -0,0 +1,482 @@
+// WPA2/WPA3-Enterprise (802.1X) connections: PEAP, TTLS and TLS, with the
+// certificates picked through the dialog plugin and passed to NetworkManager
+// as file:// references.
+use crate::networkmanager::{self as nm, NetworkError, NetworkResult, NewConnectionSettings};
+use crate::wifi::add_and_activate;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tauri_plugin_dialog::DialogExt;
+use zbus::zvariant::Value;
+use zbus::Connection;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EapMethod {
+    Peap,
+    Ttls,
+    Tls,
+}
+
+impl EapMethod {
+    fn name(self) -> &'static str {
+        match self {
+            EapMethod::Peap => "peap",
+            EapMethod::Ttls => "ttls",
+            EapMethod::Tls => "tls",
+        }
+    }
+}
+
+// Inner authentication of PEAP and TTLS
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase2Auth {
+    Mschapv2,
+    Mschap,
+    Pap,
+    Chap,
+    Gtc,
+    Md5,
+}
+
+impl Phase2Auth {
+    fn name(self) -> &'static str {
+        match self {
+            Phase2Auth::Mschapv2 => "mschapv2",
+            Phase2Auth::Mschap => "mschap",
+            Phase2Auth::Pap => "pap",
+            Phase2Auth::Chap => "chap",
+            Phase2Auth::Gtc => "gtc",
+            Phase2Auth::Md5 => "md5",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnterpriseCredentials {
+    pub eap: EapMethod,
+    pub identity: String,
+    // Outer identity sent in the clear, e.g. "anonymous@example.edu"
+    #[serde(rename = "anonymousIdentity")]
+    pub anonymous_identity: Option<String>,
+    // PEAP and TTLS
+    pub password: Option<String>,
+    #[serde(rename = "phase2Auth")]
+    pub phase2_auth: Option<Phase2Auth>,
+    #[serde(rename = "caCert")]
+    pub ca_cert: Option<String>,
+    // Server certificate must be for this domain or a subdomain of it
+    #[serde(rename = "domainSuffixMatch")]
+    pub domain_suffix_match: Option<String>,
+    // TLS; a PKCS#12 file can serve as both certificate and key
+    #[serde(rename = "clientCert")]
+    pub client_cert: Option<String>,
+    #[serde(rename = "privateKey")]
+    pub private_key: Option<String>,
+    #[serde(rename = "privateKeyPassword")]
+    pub private_key_password: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CertificateKind {
+    Ca,
+    Client,
+    PrivateKey,
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+fn is_pkcs12(path: &str) -> bool {
+    let path = path.to_lowercase();
+    path.ends_with(".p12") || path.ends_with(".pfx")
+}
+
+// NetworkManager takes certificates as a NUL-terminated file:// URI in a byte array
+fn certificate_value(path: &str, label: &str) -> NetworkResult<Value<'static>> {
+    let file = Path::new(path);
+    if !file.is_absolute() {
+        return Err(NetworkError::invalid_input(format!("The {} path must be absolute", label)));
+    }
+    if !file.is_file() {
+        return Err(NetworkError::invalid_input(format!("The {} {} does not exist", label, path)));
+    }
+
+    let mut uri = format!("file://{}", path).into_bytes();
+    uri.push(0);
+    Ok(Value::from(uri))
+}
+
+// The 802-1x setting group, after checking that the credentials are complete
+pub fn eap_settings(credentials: &EnterpriseCredentials) -> NetworkResult<HashMap<&'static str, Value<'static>>> {
+    let identity = credentials.identity.trim();
+    if identity.is_empty() {
+        return Err(NetworkError::invalid_input("An identity is required"));
+    }
+
+    let ca_cert = non_empty(&credentials.ca_cert);
+    let domain = non_empty(&credentials.domain_suffix_match);
+    if ca_cert.is_none() && domain.is_none() {
+        return Err(NetworkError::invalid_input(
+            "Choose a CA certificate or a domain to match, otherwise the server can't be verified",
+        ));
+    }
+
+    let mut settings = HashMap::new();
+    settings.insert("eap", Value::from(vec![credentials.eap.name()]));
+    settings.insert("identity", Value::from(identity.to_string()));
+    if let Some(anonymous) = non_empty(&credentials.anonymous_identity) {
+        settings.insert("anonymous-identity", Value::from(anonymous.to_string()));
+    }
+    match ca_cert {
+        Some(path) => {
+            settings.insert("ca-cert", certificate_value(path, "CA certificate")?);
+        }
+        // Verify the server against the system's trusted CAs
+        None => {
+            settings.insert("system-ca-certs", Value::from(true));
+        }
+    }
+    if let Some(domain) = domain {
+        settings.insert("domain-suffix-match", Value::from(domain.to_string()));
+    }
+
+    match credentials.eap {
+        EapMethod::Peap | EapMethod::Ttls => {
+            let password = credentials
+                .password
+                .as_deref()
+                .filter(|p| !p.is_empty())
+                .ok_or_else(|| NetworkError::invalid_input("A password is required"))?;
+            settings.insert("password", Value::from(password.to_string()));
+            let phase2 = credentials.phase2_auth.unwrap_or(Phase2Auth::Mschapv2);
+            // PAP, CHAP and MD5 only work inside a TTLS tunnel
+            let tunnels_eap_only = credentials.eap == EapMethod::Peap;
+            if tunnels_eap_only && matches!(phase2, Phase2Auth::Pap | Phase2Auth::Chap | Phase2Auth::Md5) {
+                return Err(NetworkError::invalid_input(format!(
+                    "PEAP does not support {} inner authentication, use TTLS or choose MSCHAPv2, MSCHAP or GTC",
+                    phase2.name().to_uppercase()
+                )));
+            }
+            settings.insert("phase2-auth", Value::from(phase2.name()));
+        }
+        EapMethod::Tls => {
+            let client_cert = non_empty(&credentials.client_cert)
+                .ok_or_else(|| NetworkError::invalid_input("TLS needs a client certificate"))?;
+            let private_key = match non_empty(&credentials.private_key) {
+                Some(key) => key,
+                None if is_pkcs12(client_cert) => client_cert,
+                None => return Err(NetworkError::invalid_input("TLS needs a private key")),
+            };
+            let key_password = credentials
+                .private_key_password
+                .as_deref()
+                .filter(|p| !p.is_empty());
+            if key_password.is_none() && is_pkcs12(private_key) {
+                return Err(NetworkError::invalid_input("PKCS#12 files need their password"));
+            }
+
+            settings.insert("client-cert", certificate_value(client_cert, "client certificate")?);
+            settings.insert("private-key", certificate_value(private_key, "private key")?);
+            if let Some(password) = key_password {
+                settings.insert("private-key-password", Value::from(password.to_string()));
+            }
+        }
+    }
+
+    Ok(settings)
+}
+
+// Connects to an 802.1X network, replacing the credentials of its saved
+// connection if there is one. `hidden` is for networks that don't broadcast
+// their SSID.
+pub async fn connect_enterprise(
+    conn: &Connection,
+    ssid: &str,
+    credentials: &EnterpriseCredentials,
+    hidden: bool,
+) -> NetworkResult<String> {
+    if ssid.is_empty() {
+        return Err(NetworkError::invalid_input("The SSID cannot be empty"));
+    }
+    let eap = eap_settings(credentials)?;
+    let security = HashMap::from([("key-mgmt", Value::from("wpa-eap"))]);
+    let device = nm::wifi_device(conn).await?;
+
+    if let Some(saved) = nm::saved_wifi_connections(conn, ssid).await?.into_iter().next() {
+        let mut settings = saved.editable_settings()?;
+        settings.insert("802-11-wireless-security", security);
+        settings.insert("802-1x", eap);
+        if hidden {
+            settings.entry("802-11-wireless").or_default().insert("hidden", Value::from(true));
+        }
+        nm::update_connection(conn, &saved.path, settings).await?;
+
+        let active = nm::network_manager(conn)
+            .await?
+            .activate_connection(&saved.path, &device, &nm::none_path())
+            .await?;
+        nm::wait_for_activation(conn, &active, &device, ssid).await?;
+        return Ok(format!("Connected to {}", ssid));
+    }
+
+    let ap = nm::access_points(conn, &device)
+        .await?
+        .into_iter()
+        .filter(|ap| ap.ssid == ssid.as_bytes())
+        .max_by_key(|ap| ap.strength);
+    if ap.is_none() && !hidden {
+        return Err(NetworkError::not_found(format!("Network {} not found", ssid)));
+    }
+
+    let mut wireless = HashMap::from([
+        ("ssid", Value::from(ssid.as_bytes().to_vec())),
+        ("mode", Value::from("infrastructure")),
+    ]);
+    if hidden {
+        wireless.insert("hidden", Value::from(true));
+    }
+
+    let mut settings: NewConnectionSettings = HashMap::new();
+    settings.insert(
+        "connection",
+        HashMap::from([("id", Value::from(ssid)), ("type", Value::from("802-11-wireless"))]),
+    );
+    settings.insert("802-11-wireless", wireless);
+    settings.insert("802-11-wireless-security", security);
+    settings.insert("802-1x", eap);
+
+    let specific_object = ap.map(|ap| ap.path).unwrap_or_else(|| nm::none_path().into());
+    add_and_activate(conn, &device, settings, &specific_object, ssid).await
+}
+
+#[tauri::command]
+pub async fn connect_enterprise_wifi(
+    ssid: String,
+    credentials: EnterpriseCredentials,
+    hidden: Option<bool>,
+) -> Result<String, NetworkError> {
+    connect_enterprise(&nm::system_bus().await?, &ssid, &credentials, hidden.unwrap_or(false)).await
+}
+
+// Opens a file dialog for a certificate or key; None when cancelled
+#[tauri::command]
+pub async fn pick_wifi_certificate(app_handle: tauri::AppHandle, kind: CertificateKind) -> Option<String> {
+    let (title, extensions): (&str, &[&str]) = match kind {
+        CertificateKind::Ca => ("CA certificate", &["pem", "crt", "cer", "der"]),
+        CertificateKind::Client => ("Client certificate", &["pem", "crt", "cer", "der", "p12", "pfx"]),
+        CertificateKind::PrivateKey => ("Private key", &["pem", "key", "der", "p12", "pfx"]),
+    };
+
+    let mut dialog = app_handle
+        .dialog()
+        .file()
+        .set_title(format!("Select {}", title.to_lowercase()))
+        .add_filter(title, extensions);
+    if kind == CertificateKind::Ca {
+        dialog = dialog.set_directory("/etc/ssl/certs");
+    }
+
+    // The dialog answers through a callback; waiting on a channel keeps the
+    // async runtime free while it is open
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    dialog.pick_file(move |file| {
+        let _ = sender.send(file);
+    });
+
+    receiver
+        .await
+        .ok()
+        .flatten()
+        .and_then(|file| file.into_path().ok())
+        .map(|path| path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networkmanager::NetworkErrorKind;
+    use std::path::PathBuf;
+
+    // Empty stand-ins for the certificate files, which are only checked for existence
+    fn cert_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("archion-eap-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["ca.pem", "client.pem", "client.key", "client.p12"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        dir
+    }
+
+    fn path(dir: &Path, file: &str) -> Option<String> {
+        Some(dir.join(file).display().to_string())
+    }
+
+    fn peap(dir: &Path) -> EnterpriseCredentials {
+        EnterpriseCredentials {
+            eap: EapMethod::Peap,
+            identity: "alice@example.edu".to_string(),
+            anonymous_identity: None,
+            password: Some("hunter22".to_string()),
+            phase2_auth: None,
+            ca_cert: path(dir, "ca.pem"),
+            domain_suffix_match: None,
+            client_cert: None,
+            private_key: None,
+            private_key_password: None,
+        }
+    }
+
+    fn tls(dir: &Path) -> EnterpriseCredentials {
+        EnterpriseCredentials {
+            eap: EapMethod::Tls,
+            password: None,
+            client_cert: path(dir, "client.pem"),
+            private_key: path(dir, "client.key"),
+            ..peap(dir)
+        }
+    }
+
+    fn rejected(credentials: &EnterpriseCredentials) -> String {
+        let error = eap_settings(credentials).unwrap_err();
+        assert_eq!(error.kind, NetworkErrorKind::InvalidInput, "{}", error.message);
+        error.message
+    }
+
+    #[test]
+    fn identity_and_server_verification_are_required() {
+        let dir = cert_dir("required");
+
+        let blank_identity = EnterpriseCredentials {
+            identity: "  ".to_string(),
+            ..peap(&dir)
+        };
+        assert_eq!(rejected(&blank_identity), "An identity is required");
+
+        let unverified = EnterpriseCredentials {
+            ca_cert: None,
+            domain_suffix_match: Some(" ".to_string()),
+            ..peap(&dir)
+        };
+        assert!(rejected(&unverified).contains("CA certificate or a domain"));
+
+        // A domain alone falls back to the system CAs
+        let by_domain = EnterpriseCredentials {
+            ca_cert: None,
+            domain_suffix_match: Some("radius.example.edu".to_string()),
+            ..peap(&dir)
+        };
+        let settings = eap_settings(&by_domain).unwrap();
+        assert_eq!(settings["system-ca-certs"], Value::from(true));
+        assert_eq!(settings["domain-suffix-match"], Value::from("radius.example.edu"));
+        assert!(!settings.contains_key("ca-cert"));
+
+        let settings = eap_settings(&peap(&dir)).unwrap();
+        assert_eq!(settings["identity"], Value::from("alice@example.edu"));
+        assert_eq!(settings["phase2-auth"], Value::from("mschapv2"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn peap_rejects_inner_methods_it_cannot_tunnel() {
+        let dir = cert_dir("phase2");
+
+        for phase2 in [Phase2Auth::Pap, Phase2Auth::Chap, Phase2Auth::Md5] {
+            let credentials = EnterpriseCredentials {
+                phase2_auth: Some(phase2),
+                ..peap(&dir)
+            };
+            assert!(rejected(&credentials).contains(&phase2.name().to_uppercase()));
+
+            // TTLS carries them fine
+            let ttls = EnterpriseCredentials {
+                eap: EapMethod::Ttls,
+                ..credentials
+            };
+            assert_eq!(eap_settings(&ttls).unwrap()["phase2-auth"], Value::from(phase2.name()));
+        }
+        for phase2 in [Phase2Auth::Mschapv2, Phase2Auth::Mschap, Phase2Auth::Gtc] {
+            let credentials = EnterpriseCredentials {
+                phase2_auth: Some(phase2),
+                ..peap(&dir)
+            };
+            assert!(eap_settings(&credentials).is_ok(), "{:?}", phase2);
+        }
+
+        let no_password = EnterpriseCredentials {
+            password: Some(String::new()),
+            ..peap(&dir)
+        };
+        assert_eq!(rejected(&no_password), "A password is required");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn tls_needs_a_certificate_and_key() {
+        let dir = cert_dir("tls");
+
+        let settings = eap_settings(&tls(&dir)).unwrap();
+        assert!(settings.contains_key("client-cert") && settings.contains_key("private-key"));
+        assert!(!settings.contains_key("password"));
+
+        let no_cert = EnterpriseCredentials {
+            client_cert: None,
+            ..tls(&dir)
+        };
+        assert_eq!(rejected(&no_cert), "TLS needs a client certificate");
+
+        let no_key = EnterpriseCredentials {
+            private_key: None,
+            ..tls(&dir)
+        };
+        assert_eq!(rejected(&no_key), "TLS needs a private key");
+
+        // A PKCS#12 bundle serves as both, but only with its password
+        let bundle = EnterpriseCredentials {
+            client_cert: path(&dir, "client.p12"),
+            private_key: None,
+            ..tls(&dir)
+        };
+        assert_eq!(rejected(&bundle), "PKCS#12 files need their password");
+        let bundle = EnterpriseCredentials {
+            private_key_password: Some("secret".to_string()),
+            ..bundle
+        };
+        let settings = eap_settings(&bundle).unwrap();
+        assert_eq!(settings["client-cert"], settings["private-key"]);
+        assert_eq!(settings["private-key-password"], Value::from("secret"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn certificates_must_be_existing_absolute_paths() {
+        let dir = cert_dir("paths");
+
+        let relative = EnterpriseCredentials {
+            ca_cert: Some("certs/ca.pem".to_string()),
+            ..peap(&dir)
+        };
+        assert_eq!(rejected(&relative), "The CA certificate path must be absolute");
+
+        let missing = EnterpriseCredentials {
+            private_key: path(&dir, "missing.key"),
+            ..tls(&dir)
+        };
+        assert!(rejected(&missing).ends_with("missing.key does not exist"));
+
+        // A directory is not a certificate file
+        let directory = EnterpriseCredentials {
+            ca_cert: Some(dir.display().to_string()),
+            ..peap(&dir)
+        };
+        assert!(rejected(&directory).starts_with("The CA certificate "));
+
+        let mut uri = format!("file://{}", dir.join("ca.pem").display()).into_bytes();
+        uri.push(0);
+        assert_eq!(eap_settings(&peap(&dir)).unwrap()["ca-cert"], Value::from(uri));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,235 @@
+// Workspace rules (`workspace = 1, monitor:DP-1, default:true`) kept in the
+// generated workspaces.conf, so workspaces can be bound to a monitor without
+// editing hyprland.conf by hand.
+use crate::autogen;
+use crate::hypr_sources::{escape_comment, strip_comment};
+use crate::hyprland::HyprlandClient;
+use crate::Monitor;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+const WORKSPACES_CONFIG: &str = "workspaces.conf";
+
+// A workspace as Hyprland currently has it
+#[derive(Debug, Clone, Serialize)]
+pub struct Workspace {
+    pub id: i32,
+    pub name: String,
+    pub monitor: String,
+    #[serde(rename = "monitorId")]
+    pub monitor_id: i32,
+    pub windows: i32,
+    #[serde(rename = "hasFullscreen")]
+    pub has_fullscreen: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceRule {
+    // Workspace selector as Hyprland takes it, e.g. "3", "name:web" or "special:scratch"
+    pub workspace: String,
+    // Rule target of the monitor it is bound to (connector name or `desc:...`)
+    #[serde(default)]
+    pub monitor: Option<String>,
+    // Shown on the monitor when it is first enabled
+    #[serde(default)]
+    pub default: bool,
+    // Kept alive even when it has no windows
+    #[serde(default)]
+    pub persistent: bool,
+    #[serde(default, rename = "gapsIn")]
+    pub gaps_in: Option<i32>,
+    #[serde(default, rename = "gapsOut")]
+    pub gaps_out: Option<i32>,
+}
+
+// Formats a rule without the `workspace =` prefix
+pub fn format_workspace_rule(rule: &WorkspaceRule) -> String {
+    let mut line = rule.workspace.clone();
+
+    if let Some(monitor) = &rule.monitor {
+        line.push_str(&format!(", monitor:{}", monitor));
+    }
+    if rule.default {
+        line.push_str(", default:true");
+    }
+    if rule.persistent {
+        line.push_str(", persistent:true");
+    }
+    if let Some(gaps_in) = rule.gaps_in {
+        line.push_str(&format!(", gapsin:{}", gaps_in));
+    }
+    if let Some(gaps_out) = rule.gaps_out {
+        line.push_str(&format!(", gapsout:{}", gaps_out));
+    }
+
+    line
+}
+
+fn format_workspace_config(rules: &[WorkspaceRule]) -> String {
+    let mut config_content = String::from(autogen::AUTOGEN_HEADER);
+    for rule in rules {
+        config_content.push_str(&format!("workspace = {}\n", escape_comment(&format_workspace_rule(rule))));
+    }
+    config_content
+}
+
+pub fn parse_workspace_config(content: &str) -> Vec<WorkspaceRule> {
+    let mut rules = Vec::new();
+
+    for line in content.lines() {
+        let line = strip_comment(line);
+        let line = line.trim();
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if key.trim() != "workspace" {
+            continue;
+        }
+
+        let mut args = value.split(',').map(|a| a.trim());
+        let workspace = args.next().unwrap_or("");
+        if workspace.is_empty() {
+            continue;
+        }
+
+        let mut rule = WorkspaceRule {
+            workspace: workspace.to_string(),
+            monitor: None,
+            default: false,
+            persistent: false,
+            gaps_in: None,
+            gaps_out: None,
+        };
+
+        for arg in args {
+            // `monitor:desc:...` keeps everything after the first colon
+            let Some((key, value)) = arg.split_once(':') else {
+                continue;
+            };
+            match key.trim() {
+                "monitor" => rule.monitor = Some(value.trim().to_string()),
+                "default" => rule.default = parse_bool(value),
+                "persistent" => rule.persistent = parse_bool(value),
+                "gapsin" => rule.gaps_in = value.trim().parse().ok(),
+                "gapsout" => rule.gaps_out = value.trim().parse().ok(),
+                _ => {}
+            }
+        }
+
+        rules.push(rule);
+    }
+
+    rules
+}
+
+fn parse_bool(value: &str) -> bool {
+    matches!(value.trim(), "true" | "1" | "yes" | "on")
+}
+
+pub fn read_workspace_config() -> Result<Vec<WorkspaceRule>, String> {
+    let config_file = autogen::autogen_dir().join(WORKSPACES_CONFIG);
+    if !config_file.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&config_file)
+        .map_err(|e| format!("Failed to read {}: {}", config_file.display(), e))?;
+    Ok(parse_workspace_config(&content))
+}
+
+pub fn validate_workspace_rules(rules: &[WorkspaceRule]) -> Result<(), String> {
+    for (i, rule) in rules.iter().enumerate() {
+        let workspace = rule.workspace.trim();
+        if workspace.is_empty() {
+            return Err("Workspace cannot be empty".to_string());
+        }
+        if workspace.contains(',') || workspace.contains('\n') || workspace.contains('#') {
+            return Err(format!("Invalid workspace: {}", workspace));
+        }
+        if rule.gaps_in.is_some_and(|g| g < 0) || rule.gaps_out.is_some_and(|g| g < 0) {
+            return Err(format!("Gaps of workspace {} cannot be negative", workspace));
+        }
+
+        for other in &rules[..i] {
+            if other.workspace.trim() != workspace {
+                continue;
+            }
+            if other.monitor.is_some() && rule.monitor.is_some() && other.monitor != rule.monitor {
+                return Err(format!("Workspace {} is bound to more than one monitor", workspace));
+            }
+        }
+
+        if rule.default
+            && rules[..i]
+                .iter()
+                .any(|other| other.default && other.monitor.is_some() && other.monitor == rule.monitor)
+        {
+            return Err(format!(
+                "{} has more than one default workspace",
+                rule.monitor.as_deref().unwrap_or("")
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Whether a saved rule targets this monitor, by connector name or description
+fn targets_monitor(rule: &WorkspaceRule, monitor: &Monitor) -> bool {
+    let Some(target) = rule.monitor.as_deref() else {
+        return false;
+    };
+    match target.strip_prefix("desc:") {
+        Some(description) => !monitor.description.is_empty() && description == monitor.description,
+        None => target == monitor.name,
+    }
+}
+
+#[tauri::command]
+pub fn get_workspaces() -> Result<Vec<Workspace>, String> {
+    let workspaces: Vec<serde_json::Value> = HyprlandClient::from_env()?
+        .json("workspaces")
+        .map_err(|e| format!("Failed to get workspaces from Hyprland: {}", e))?;
+
+    let mut result: Vec<Workspace> = workspaces
+        .iter()
+        .map(|workspace| Workspace {
+            id: workspace["id"].as_i64().unwrap_or(0) as i32,
+            name: workspace["name"].as_str().unwrap_or("").to_string(),
+            monitor: workspace["monitor"].as_str().unwrap_or("").to_string(),
+            monitor_id: workspace["monitorID"].as_i64().unwrap_or(-1) as i32,
+            windows: workspace["windows"].as_i64().unwrap_or(0) as i32,
+            has_fullscreen: workspace["hasfullscreen"].as_bool().unwrap_or(false),
+        })
+        .collect();
+    result.sort_by_key(|w| w.id);
+
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn get_workspace_rules() -> Result<Vec<WorkspaceRule>, String> {
+    read_workspace_config()
+}
+
+// Replaces the rules bound to `monitor` with `rules`, keeping the rules of
+// every other monitor, and returns everything that was saved
+#[tauri::command]
+pub fn save_workspace_rules(monitor: Monitor, rules: Vec<WorkspaceRule>) -> Result<Vec<WorkspaceRule>, String> {
+    let target = monitor.rule_target();
+
+    let mut saved: Vec<WorkspaceRule> = read_workspace_config()?
+        .into_iter()
+        .filter(|rule| !targets_monitor(rule, &monitor))
+        .collect();
+    saved.extend(rules.into_iter().map(|rule| WorkspaceRule {
+        workspace: rule.workspace.trim().to_string(),
+        monitor: Some(target.clone()),
+        ..rule
+    }));
+
+    validate_workspace_rules(&saved)?;
+    autogen::write_autogen_file(WORKSPACES_CONFIG, &format_workspace_config(&saved))?;
+
+    Ok(saved)
+}
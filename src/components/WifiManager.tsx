@@ -29,6 +29,18 @@ interface WifiStatus {
   interface: string;
 }
 
+interface NetworkError {
+  kind: string;
+  message: string;
+}
+
+const errorMessage = (err: unknown): string => {
+  if (typeof err === 'object' && err !== null && 'message' in err) {
+    return (err as NetworkError).message;
+  }
+  return String(err);
+};
+
 export const WifiManager: React.FC = () => {
   const [wifiStatus, setWifiStatus] = useState<WifiStatus | null>(null);
   const [networks, setNetworks] = useState<WifiNetwork[]>([]);
@@ -44,7 +56,7 @@ export const WifiManager: React.FC = () => {
       setWifiStatus(status);
     } catch (err) {
       console.error('Failed to get WiFi status:', err);
-      setError(err as string);
+      setError(errorMessage(err));
     }
   };
 
@@ -60,7 +72,7 @@ export const WifiManager: React.FC = () => {
       setNetworks(sortedNetworks);
     } catch (err) {
       console.error('Failed to get WiFi networks:', err);
-      setError(err as string);
+      setError(errorMessage(err));
     }
   };
 
@@ -76,7 +88,7 @@ export const WifiManager: React.FC = () => {
       }, 2000);
     } catch (err) {
       console.error('Failed to refresh WiFi networks:', err);
-      setError(err as string);
+      setError(errorMessage(err));
       setLoading(false);
     }
   };
@@ -125,7 +137,7 @@ export const WifiManager: React.FC = () => {
       await invoke('toggle_wifi', { enable: !wifiStatus.enabled });
       await refreshData();
     } catch (err) {
-      setError(err as string);
+      setError(errorMessage(err));
     }
   };
 
@@ -140,7 +152,7 @@ export const WifiManager: React.FC = () => {
       await invoke('connect_wifi', { ssid, password: null });
       await refreshData();
     } catch (err) {
-      setError(err as string);
+      setError(errorMessage(err));
     } finally {
       setConnectingTo(null);
     }
@@ -159,7 +171,7 @@ export const WifiManager: React.FC = () => {
       setShowPasswordDialog(null);
       setPassword('');
     } catch (err) {
-      setError(err as string);
+      setError(errorMessage(err));
     } finally {
       setConnectingTo(null);
     }
@@ -170,7 +182,7 @@ export const WifiManager: React.FC = () => {
       await invoke('disconnect_wifi', { ssid });
       await refreshData();
     } catch (err) {
-      setError(err as string);
+      setError(errorMessage(err));
     }
   };
 
@@ -179,7 +191,7 @@ export const WifiManager: React.FC = () => {
       await invoke('forget_wifi', { ssid });
       await refreshData();
     } catch (err) {
-      setError(err as string);
+      setError(errorMessage(err));
     }
   };
 
@@ -12,6 +12,27 @@ export interface Monitor {
   disabled: boolean;
   scale: number;
   availableModes: string[];
+  transform?: number; // 0-7, see Hyprland's monitor `transform` argument
+  mirror?: string | null;
+  bitdepth?: number | null;
+  vrr?: number | null;
+  cm?: string | null;
+  description?: string;
+  make?: string;
+  model?: string;
+  serial?: string;
+  matchByDescription?: boolean; // Save the rule as desc:<description> instead of the connector name
+  modes?: DisplayMode[]; // availableModes parsed by the backend
+  modeline?: string | null; // Custom modeline, overrides width/height/refreshRate when set
+  virtual?: boolean; // Headless or nested Wayland output created with `output create`
+  gpu?: string | null; // DRM card driving the output, e.g. "card1"
+  iccProfile?: string | null; // Path of the ICC profile written as the rule's `icc` argument
+}
+
+export interface DisplayMode {
+  width: number;
+  height: number;
+  refreshMhz: number;
 }
 
 export interface DisplaySettings {